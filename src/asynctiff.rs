@@ -0,0 +1,283 @@
+#![allow(dead_code)]
+
+use std::collections::HashSet;
+use std::io::{Cursor, Read, SeekFrom};
+use std::path::Path;
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncSeek, BufReader};
+use tokio::sync::Mutex;
+use crate::{TiffError, Header, IFD, ChunkCache};
+use crate::decode::{ChunkDecoder, decode_chunks};
+use crate::ifd::{Baseline, Extension, Tag, TagData, TagID, PointerSlot, MAX_SUB_IFD_DEPTH, is_ifd_pointer};
+use crate::tiff::{RawChunk, chunk_tags, region_chunks, assemble_region};
+
+async fn read_bytes<R: AsyncRead + AsyncSeek + Unpin>(buffer: &mut R, pos: u64, len: usize) -> Result<Vec<u8>, TiffError> {
+    // called through the traits so they don't shadow std::io::Read on Cursor below
+    tokio::io::AsyncSeekExt::seek(buffer, SeekFrom::Start(pos)).await.or(Err(TiffError::UnexpectedEndOfBuffer))?;
+    let mut data = vec![0u8; len];
+    tokio::io::AsyncReadExt::read_exact(buffer, &mut data).await.or(Err(TiffError::UnexpectedEndOfBuffer))?;
+    Ok(data)
+}
+
+async fn read_header<R: AsyncRead + AsyncSeek + Unpin>(buffer: &mut R) -> Result<Header, TiffError> {
+    let mut data = read_bytes(buffer, 0, 8).await?;
+    // BigTIFF headers carry an 8 byte first IFD offset after the version
+    if data[2..4] == [43, 0] || data[2..4] == [0, 43] {
+        data.extend(read_bytes(buffer, 8, 8).await?);
+    }
+    Header::from_buffer(&mut Cursor::new(data))
+}
+
+// Fetches the directory block and decodes its entries with the same parser as `IFD`;
+// out-of-line values up to `threshold` bytes and IFD pointers are then fetched.
+// IFDs already in `visited` are not read again, as in `IFD::from_buffer_deferred`.
+async fn read_ifd<R: AsyncRead + AsyncSeek + Unpin + Send>(buffer: &mut R, pos: u64, header: &Header, skip: bool, threshold: usize, depth: usize, visited: &mut HashSet<u64>) -> Result<IFD, TiffError> {
+    visited.insert(pos);
+    let btf = header.is_btf();
+    let le = header.is_le();
+    let (count_size, entry_size, offset_size) = if btf { (8, 20, 8) } else { (2, 12, 4) };
+    let mut count = Cursor::new(read_bytes(buffer, pos, count_size).await?);
+    let tag_count = if btf {
+        buffer_as!(count, u64, le)?
+    } else {
+        buffer_as!(count, u16, le)? as u64
+    };
+    let entry_byte_count = (tag_count as usize).checked_mul(entry_size).ok_or(TiffError::UnexpectedEndOfBuffer)?;
+    // the count comes from the file: check the block fits before allocating it
+    let size = tokio::io::AsyncSeekExt::seek(buffer, SeekFrom::End(0)).await.or(Err(TiffError::UnexpectedEndOfBuffer))?;
+    if (entry_byte_count + offset_size) as u64 > size.saturating_sub(pos + count_size as u64) {
+        return Err(TiffError::UnexpectedEndOfBuffer);
+    }
+    let mut entries = Cursor::new(read_bytes(buffer, pos + count_size as u64, entry_byte_count + offset_size).await?);
+
+    let mut tags: Vec<Tag> = vec![];
+    let mut pointers: Vec<(u16, Vec<u64>)> = vec![];
+    for _ in 0..tag_count {
+        let mut tag = Tag::from_entry(&mut entries, btf, le)?;
+        let id = tag.id().to_u16();
+        let is_pointer = is_ifd_pointer(id);
+        // with skip, only IFD pointer entries are decoded so the IFD tree is still complete
+        if skip && !is_pointer {
+            continue;
+        }
+        if let Some(deferred) = tag.deferred().cloned() {
+            if is_pointer || !tag.defers(threshold)? {
+                tag = tag.resolve(&read_bytes(buffer, deferred.offset, deferred.byte_count()?).await?, le)?;
+            }
+        }
+        if is_pointer {
+            pointers.push((id, tag.data()?.as_unsigned_integers()?));
+        }
+        if !skip {
+            tags.push(tag);
+        }
+    }
+    let next_ifd = buffer_as_offset!(entries, btf, le);
+
+    let mut directory = IFD{pos, tag_count, tags, next_ifd, ..Default::default()};
+    if depth < MAX_SUB_IFD_DEPTH {
+        for (id, offsets) in pointers {
+            let threshold = PointerSlot::from_id(id).map_or(threshold, |slot| slot.threshold(threshold));
+            for offset in offsets {
+                if !visited.insert(offset) {
+                    continue;
+                }
+                // a broken pointer leaves the child unset instead of failing the whole file
+                if let Ok(child) = Box::pin(read_ifd(buffer, offset, header, skip, threshold, depth + 1, visited)).await {
+                    directory.attach(id, child);
+                }
+            }
+        }
+    }
+    Ok(directory)
+}
+
+// Async counterpart of `Tiff` over tokio's AsyncRead + AsyncSeek.
+#[derive(Debug)]
+pub struct AsyncTiff<R> {
+    pub path: String,
+    pub header: Header,
+    pub ifd: Vec<IFD>,
+    // bulky out-of-line tag values above this many bytes are left unread by read_frame
+    pub defer_threshold: Option<usize>,
+    reader: Mutex<R>,
+    // only held between awaits, so a std Mutex is enough
+    cache: Option<std::sync::Mutex<ChunkCache>>,
+}
+impl AsyncTiff<BufReader<tokio::fs::File>> {
+    pub async fn from_path(path: &Path, skip: bool) -> Result<Self, TiffError> {
+        let file = tokio::fs::File::open(path).await.or(Err(TiffError::CannotOpenFile))?;
+        let mut tiff = Self::from_reader(BufReader::new(file), skip).await?;
+        tiff.path = String::from(path.to_str().unwrap_or(""));
+        Ok(tiff)
+    }
+}
+impl<R: AsyncRead + AsyncSeek + Unpin + Send> AsyncTiff<R> {
+    pub async fn from_reader(buffer: R, skip: bool) -> Result<Self, TiffError> {
+        Self::from_reader_limit(buffer, skip, usize::MAX, None).await
+    }
+    // Like `from_reader`, leaving bulky out-of-line values above `threshold` bytes unread
+    // (see `defer_threshold`) in the IFDs parsed at open as well.
+    pub async fn from_reader_with_threshold(buffer: R, skip: bool, threshold: usize) -> Result<Self, TiffError> {
+        Self::from_reader_limit(buffer, skip, usize::MAX, Some(threshold)).await
+    }
+    // Reads the header and the first IFD only; the rest can be walked with `iter`.
+    pub async fn from_reader_first(buffer: R, skip: bool) -> Result<Self, TiffError> {
+        Self::from_reader_limit(buffer, skip, 1, None).await
+    }
+    async fn from_reader_limit(mut buffer: R, skip: bool, limit: usize, defer_threshold: Option<usize>) -> Result<Self, TiffError> {
+        let header = read_header(&mut buffer).await?;
+        let mut next_ifd = header.first_ifd;
+        let mut ifd: Vec<IFD> = vec![];
+        let mut visited: HashSet<u64> = HashSet::new();
+        while next_ifd != 0 && ifd.len() < limit {
+            if !visited.insert(next_ifd) {
+                return Err(TiffError::CircularIFDChain);
+            }
+            let directory = read_ifd(&mut buffer, next_ifd, &header, skip, defer_threshold.unwrap_or(usize::MAX), 0, &mut HashSet::new()).await?;
+            next_ifd = directory.next_ifd;
+            ifd.push(directory);
+        }
+        Ok(Self{path: String::new(), header, ifd, defer_threshold, reader: Mutex::new(buffer), cache: None})
+    }
+    pub fn len(&self) -> usize {
+        self.ifd.len()
+    }
+    // Walks the whole IFD chain from the header, as `Tiff::iter`.
+    pub fn iter(&self, skip: bool) -> AsyncIFDIter<'_, R> {
+        AsyncIFDIter{tiff: self, next_ifd: self.header.first_ifd, skip, visited: HashSet::new()}
+    }
+    // Keeps up to `budget` bytes of decoded strips/tiles; 0 disables the cache.
+    pub fn set_cache_budget(&mut self, budget: usize) {
+        self.cache = match budget {
+            0 => None,
+            _ => Some(std::sync::Mutex::new(ChunkCache::new(budget))),
+        };
+    }
+    pub async fn read_frame(&self, index: usize) -> Result<IFD, TiffError> {
+        if index >= self.len() {
+            return Err(TiffError::InvalidIndex);
+        }
+        let threshold = self.defer_threshold.unwrap_or(usize::MAX);
+        let mut reader = self.reader.lock().await;
+        read_ifd(&mut *reader, self.ifd[index].pos, &self.header, false, threshold, 0, &mut HashSet::new()).await
+    }
+    // Value of `tag`, fetching it first if it was deferred.
+    pub async fn load_tag<'t>(&self, tag: &'t Tag) -> Result<&'t TagData, TiffError> {
+        match tag.deferred() {
+            Some(deferred) if !tag.is_loaded() => {
+                let bytes = read_bytes(&mut *self.reader.lock().await, deferred.offset, deferred.byte_count()?).await?;
+                tag.load_bytes(&bytes, self.header.is_le())
+            },
+            _ => tag.data(),
+        }
+    }
+    // Offsets and byte counts of all the strips or tiles, given their tags.
+    async fn chunk_locations(&self, ifd: &IFD, offsets: TagID, byte_counts: TagID) -> Result<(Vec<u64>, Vec<u64>), TiffError> {
+        let offsets = self.load_tag(ifd.get_tag(offsets)?).await?.as_unsigned_integers()?;
+        let byte_counts = self.load_tag(ifd.get_tag(byte_counts)?).await?.as_unsigned_integers()?;
+        Ok((offsets, byte_counts))
+    }
+    async fn read_raw(&self, offsets: &[u64], byte_counts: &[u64], index: usize) -> Result<Vec<u8>, TiffError> {
+        let (offset, byte_count) = match (offsets.get(index), byte_counts.get(index)) {
+            (Some(offset), Some(byte_count)) => (*offset, *byte_count as usize),
+            _ => return Err(TiffError::InvalidIndex),
+        };
+        // sparse chunk, decoded as DefaultImageColor
+        if offset == 0 || byte_count == 0 {
+            return Ok(vec![]);
+        }
+        read_bytes(&mut *self.reader.lock().await, offset, byte_count).await
+    }
+    async fn raw_chunk(&self, ifd: &IFD, data: Vec<u8>) -> Result<RawChunk, TiffError> {
+        let jpeg_tables = match ifd.get_tag(TagID::ExtensionTag(Extension::JPEGTables)) {
+            Ok(tag) => Some(self.load_tag(tag).await?.as_unsigned_integers()?.iter().map(|v| *v as u8).collect()),
+            Err(_) => None,
+        };
+        Ok(RawChunk{data, compression: ifd.compression_number()?, jpeg_tables})
+    }
+    // Stored (still compressed) bytes of one strip, as `Tiff::read_raw_strip`.
+    pub async fn read_raw_strip(&self, ifd: &IFD, index: usize) -> Result<RawChunk, TiffError> {
+        let (offsets, byte_counts) = self.chunk_locations(ifd, TagID::BaselineTag(Baseline::StripOffsets), TagID::BaselineTag(Baseline::StripByteCounts)).await?;
+        let data = self.read_raw(&offsets, &byte_counts, index).await?;
+        self.raw_chunk(ifd, data).await
+    }
+    // Stored (still compressed) bytes of one tile, as `Tiff::read_raw_tile`.
+    pub async fn read_raw_tile(&self, ifd: &IFD, index: usize) -> Result<RawChunk, TiffError> {
+        let (offsets, byte_counts) = self.chunk_locations(ifd, TagID::ExtensionTag(Extension::TileOffsets), TagID::ExtensionTag(Extension::TileByteCounts)).await?;
+        let data = self.read_raw(&offsets, &byte_counts, index).await?;
+        self.raw_chunk(ifd, data).await
+    }
+    // Reads and decodes one strip (or tile, for tiled images) of `ifd`, going
+    // through the chunk cache when one is set.
+    pub async fn read_chunk(&self, ifd: &IFD, index: usize) -> Result<Arc<Vec<u8>>, TiffError> {
+        let mut chunks = self.read_chunks(ifd, &[index]).await?;
+        chunks.pop().ok_or(TiffError::InvalidIndex)
+    }
+    // Like `read_chunk` for several chunks: stored bytes are fetched one after the other,
+    // then decoded together on tokio's blocking pool.
+    pub async fn read_chunks(&self, ifd: &IFD, indices: &[usize]) -> Result<Vec<Arc<Vec<u8>>>, TiffError> {
+        let mut chunks: Vec<Option<Arc<Vec<u8>>>> = vec![None; indices.len()];
+        if let Some(cache) = &self.cache {
+            let mut cache = cache.lock().or(Err(TiffError::UnknownBufferError))?;
+            for (chunk, index) in chunks.iter_mut().zip(indices) {
+                *chunk = cache.get((ifd.pos, *index));
+            }
+        }
+        let missing: Vec<usize> = (0..indices.len()).filter(|i| chunks[*i].is_none()).collect();
+        let (offsets, byte_counts) = chunk_tags(ifd);
+        let (offsets, byte_counts) = self.chunk_locations(ifd, offsets, byte_counts).await?;
+        let mut raw: Vec<Vec<u8>> = vec![];
+        for i in &missing {
+            raw.push(self.read_raw(&offsets, &byte_counts, indices[*i]).await?);
+        }
+        let decoder = ChunkDecoder::from_ifd(ifd, self.header.is_le())?;
+        let decoded = tokio::task::spawn_blocking(move || decode_chunks(&decoder, raw)).await
+            .or(Err(TiffError::UnknownBufferError))??;
+        for (i, data) in missing.into_iter().zip(decoded) {
+            let data = Arc::new(data);
+            if let Some(cache) = &self.cache {
+                cache.lock().or(Err(TiffError::UnknownBufferError))?.insert((ifd.pos, indices[i]), data.clone());
+            }
+            chunks[i] = Some(data);
+        }
+        Ok(chunks.into_iter().flatten().collect())
+    }
+    // Reads a rectangle of chunky, byte-aligned pixels, as `Tiff::read_region`.
+    pub async fn read_region(&self, ifd: &IFD, x: u64, y: u64, width: u64, height: u64) -> Result<Vec<u8>, TiffError> {
+        let cells = region_chunks(ifd, x, y, width, height)?;
+        let indices: Vec<usize> = cells.iter().map(|(_, _, index)| *index).collect();
+        let chunks = self.read_chunks(ifd, &indices).await?;
+        assemble_region(ifd, x, y, width, height, &cells, &chunks)
+    }
+    pub async fn read_image(&self, ifd: &IFD) -> Result<Vec<u8>, TiffError> {
+        self.read_region(ifd, 0, 0, ifd.width()?, ifd.height()?).await
+    }
+}
+
+pub struct AsyncIFDIter<'a, R> {
+    tiff: &'a AsyncTiff<R>,
+    next_ifd: u64,
+    skip: bool,
+    // offsets already walked, to stop on a next IFD pointer that loops back
+    visited: HashSet<u64>,
+}
+impl<R: AsyncRead + AsyncSeek + Unpin + Send> AsyncIFDIter<'_, R> {
+    pub async fn next(&mut self) -> Option<Result<IFD, TiffError>> {
+        if self.next_ifd == 0 {
+            return None;
+        }
+        if !self.visited.insert(self.next_ifd) {
+            self.next_ifd = 0;
+            return Some(Err(TiffError::CircularIFDChain));
+        }
+        let threshold = self.tiff.defer_threshold.unwrap_or(usize::MAX);
+        let mut reader = self.tiff.reader.lock().await;
+        let directory = read_ifd(&mut *reader, self.next_ifd, &self.tiff.header, self.skip, threshold, 0, &mut HashSet::new()).await;
+        self.next_ifd = match &directory {
+            Ok(directory) => directory.next_ifd,
+            Err(_) => 0,
+        };
+        Some(directory)
+    }
+}
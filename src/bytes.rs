@@ -0,0 +1,194 @@
+#![allow(dead_code)]
+
+use std::collections::HashSet;
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use crate::{TiffError, Header, IFD};
+use crate::ifd::{Baseline, Extension, Tag, TagData, TagID, PointerSlot, MAX_SUB_IFD_DEPTH, is_ifd_pointer};
+
+fn slice_at(data: &[u8], offset: u64, len: u64) -> Result<&[u8], TiffError> {
+    let start = usize::try_from(offset).or(Err(TiffError::UnexpectedEndOfBuffer))?;
+    let len = usize::try_from(len).or(Err(TiffError::UnexpectedEndOfBuffer))?;
+    let end = start.checked_add(len).ok_or(TiffError::UnexpectedEndOfBuffer)?;
+    data.get(start..end).ok_or(TiffError::UnexpectedEndOfBuffer)
+}
+
+// A tag whose value is a view into the input; it is only decoded on `data()`.
+#[derive(Debug)]
+pub struct TagBytes<'a> {
+    pub id: TagID,
+    pub kind: u16,
+    pub count: u64,
+    pub bytes: &'a [u8],
+    // file offset of an out-of-line value
+    pub offset: Option<u64>,
+    le: bool,
+}
+impl<'a> TagBytes<'a> {
+    pub fn from_buffer(buffer: &mut Cursor<&'a [u8]>, btf: bool, le: bool) -> Result<Self, TiffError> {
+        let id = TagID::from_u16(buffer_as!(buffer, u16, le)?);
+        let kind = buffer_as!(buffer, u16, le)?;
+        let count = buffer_as_offset!(buffer, btf, le);
+        let byte_count = count.checked_mul(TagData::size_of_kind(kind)? as u64).ok_or(TiffError::UnexpectedEndOfBuffer)?;
+        let offset_byte_count: u64 = if btf { 8 } else { 4 };
+        let data: &'a [u8] = buffer.get_ref();
+        let (bytes, offset) = if byte_count > offset_byte_count {
+            let offset = buffer_as_offset!(buffer, btf, le);
+            (slice_at(data, offset, byte_count)?, Some(offset))
+        } else {
+            let pos = buffer.position();
+            buffer.seek(SeekFrom::Current(offset_byte_count as i64)).or(Err(TiffError::UnexpectedEndOfBuffer))?;
+            (slice_at(data, pos, byte_count)?, None)
+        };
+        Ok(Self{id, kind, count, bytes, offset, le})
+    }
+    pub fn data(&self) -> Result<TagData, TiffError> {
+        TagData::from_bytes(self.kind, self.count as usize, self.bytes, self.le)
+    }
+}
+
+#[derive(Debug)]
+pub struct IFDBytes<'a> {
+    pub pos: u64,
+    pub tag_count: u64,
+    pub tags: Vec<TagBytes<'a>>,
+    pub next_ifd: u64,
+    pub sub_ifd: Vec<IFDBytes<'a>>,
+    pub exif_ifd: Option<Box<IFDBytes<'a>>>,
+    pub gps_ifd: Option<Box<IFDBytes<'a>>>,
+    pub interop_ifd: Option<Box<IFDBytes<'a>>>,
+    data: &'a [u8],
+}
+impl<'a> IFDBytes<'a> {
+    pub fn from_buffer(buffer: &mut Cursor<&'a [u8]>, btf: bool, le: bool) -> Result<Self, TiffError> {
+        let mut visited: HashSet<u64> = HashSet::new();
+        Self::from_buffer_depth(buffer, btf, le, 0, &mut visited)
+    }
+    // Same tree walk as `IFD`: IFDs already in `visited` are not parsed again.
+    fn from_buffer_depth(buffer: &mut Cursor<&'a [u8]>, btf: bool, le: bool, depth: usize, visited: &mut HashSet<u64>) -> Result<Self, TiffError> {
+        let pos = buffer.position();
+        visited.insert(pos);
+        let tag_count = if btf {
+            buffer_as!(buffer, u64, le)?
+        } else {
+            buffer_as!(buffer, u16, le)? as u64
+        };
+        let mut tags: Vec<TagBytes<'a>> = vec![];
+        for _ in 0..tag_count {
+            tags.push(TagBytes::from_buffer(buffer, btf, le)?);
+        }
+        let next_ifd = buffer_as_offset!(buffer, btf, le);
+        let mut directory = Self{pos, tag_count, tags, next_ifd, sub_ifd: vec![], exif_ifd: None, gps_ifd: None, interop_ifd: None, data: buffer.get_ref()};
+        if depth < MAX_SUB_IFD_DEPTH {
+            let end = buffer.position();
+            let mut pointers: Vec<(u16, Vec<u64>)> = vec![];
+            for tag in directory.tags.iter().filter(|tag| is_ifd_pointer(tag.id.to_u16())) {
+                pointers.push((tag.id.to_u16(), tag.data()?.as_unsigned_integers()?));
+            }
+            for (id, offsets) in pointers {
+                for offset in offsets {
+                    if !visited.insert(offset) {
+                        continue;
+                    }
+                    buffer.set_position(offset);
+                    if let Ok(child) = Self::from_buffer_depth(buffer, btf, le, depth + 1, visited) {
+                        directory.attach(id, child);
+                    }
+                }
+            }
+            buffer.set_position(end);
+        }
+        Ok(directory)
+    }
+    fn retag(&mut self, f: impl Fn(&TagID) -> TagID) {
+        for tag in self.tags.iter_mut() {
+            tag.id = f(&tag.id);
+        }
+    }
+    // Same placement as `IFD::attach`.
+    fn attach(&mut self, id: u16, mut child: IFDBytes<'a>) {
+        let slot = match PointerSlot::from_id(id) {
+            Some(slot) => slot,
+            None => return,
+        };
+        child.retag(|tag| slot.retag(tag));
+        match slot {
+            PointerSlot::SubIFD => self.sub_ifd.push(child),
+            PointerSlot::Exif => self.exif_ifd = Some(Box::new(child)),
+            PointerSlot::Gps => self.gps_ifd = Some(Box::new(child)),
+            PointerSlot::Interop => self.interop_ifd = Some(Box::new(child)),
+        }
+    }
+    pub fn get_tag(&self, id: TagID) -> Result<&TagBytes<'a>, TiffError> {
+        self.tags.iter().find(|tag| tag.id == id).ok_or(TiffError::CannotFindTag)
+    }
+    // Decodes every tag into an owned IFD, for the typed accessors.
+    pub fn to_ifd(&self) -> Result<IFD, TiffError> {
+        let mut tags: Vec<Tag> = vec![];
+        for tag in &self.tags {
+            tags.push(Tag::new(tag.id.clone(), tag.data()?).with_offset(tag.offset));
+        }
+        let mut sub_ifd: Vec<IFD> = vec![];
+        for child in &self.sub_ifd {
+            sub_ifd.push(child.to_ifd()?);
+        }
+        let exif_ifd = match &self.exif_ifd {
+            Some(child) => Some(Box::new(child.to_ifd()?)),
+            None => None,
+        };
+        let gps_ifd = match &self.gps_ifd {
+            Some(child) => Some(Box::new(child.to_ifd()?)),
+            None => None,
+        };
+        let interop_ifd = match &self.interop_ifd {
+            Some(child) => Some(Box::new(child.to_ifd()?)),
+            None => None,
+        };
+        Ok(IFD{pos: self.pos, tag_count: self.tag_count, tags, next_ifd: self.next_ifd, sub_ifd, exif_ifd, gps_ifd, interop_ifd})
+    }
+    fn chunk(&self, offsets: TagID, byte_counts: TagID, index: usize) -> Result<&'a [u8], TiffError> {
+        let offsets = self.get_tag(offsets)?.data()?.as_unsigned_integers()?;
+        let byte_counts = self.get_tag(byte_counts)?.data()?.as_unsigned_integers()?;
+        match (offsets.get(index), byte_counts.get(index)) {
+            (Some(offset), Some(byte_count)) => slice_at(self.data, *offset, *byte_count),
+            _ => Err(TiffError::InvalidIndex),
+        }
+    }
+    pub fn strip(&self, index: usize) -> Result<&'a [u8], TiffError> {
+        self.chunk(TagID::BaselineTag(Baseline::StripOffsets), TagID::BaselineTag(Baseline::StripByteCounts), index)
+    }
+    pub fn tile(&self, index: usize) -> Result<&'a [u8], TiffError> {
+        self.chunk(TagID::ExtensionTag(Extension::TileOffsets), TagID::ExtensionTag(Extension::TileByteCounts), index)
+    }
+}
+
+// Zero-copy counterpart of `Tiff`: tag values and strip/tile data borrow from `data`.
+#[derive(Debug)]
+pub struct TiffBytes<'a> {
+    pub data: &'a [u8],
+    pub header: Header,
+    pub ifd: Vec<IFDBytes<'a>>,
+}
+impl<'a> TiffBytes<'a> {
+    pub fn from_bytes(data: &'a [u8]) -> Result<Self, TiffError> {
+        let mut buffer = Cursor::new(data);
+        let header = Header::from_buffer(&mut buffer)?;
+        let btf = header.is_btf();
+        let le = header.is_le();
+        let mut next_ifd = header.first_ifd;
+        let mut ifd: Vec<IFDBytes<'a>> = vec![];
+        let mut visited: HashSet<u64> = HashSet::new();
+        while next_ifd != 0 {
+            if !visited.insert(next_ifd) {
+                return Err(TiffError::CircularIFDChain);
+            }
+            buffer.seek(SeekFrom::Start(next_ifd)).or(Err(TiffError::UnexpectedEndOfBuffer))?;
+            let directory = IFDBytes::from_buffer(&mut buffer, btf, le)?;
+            next_ifd = directory.next_ifd;
+            ifd.push(directory);
+        }
+        Ok(Self{data, header, ifd})
+    }
+    pub fn len(&self) -> usize {
+        self.ifd.len()
+    }
+}
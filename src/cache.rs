@@ -0,0 +1,69 @@
+#![allow(dead_code)]
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+
+// LRU cache of decoded strips/tiles keyed by (IFD position, chunk index),
+// bounded by the total number of bytes held.
+pub struct ChunkCache {
+    budget: usize,
+    used: usize,
+    entries: HashMap<(u64, usize), Arc<Vec<u8>>>,
+    order: VecDeque<(u64, usize)>,
+}
+impl std::fmt::Debug for ChunkCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        f.debug_struct("ChunkCache")
+         .field("budget", &self.budget)
+         .field("used", &self.used)
+         .field("entries", &self.entries.len())
+         .finish()
+    }
+}
+impl ChunkCache {
+    pub fn new(budget: usize) -> Self {
+        Self{budget, used: 0, entries: HashMap::new(), order: VecDeque::new()}
+    }
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+    pub fn used(&self) -> usize {
+        self.used
+    }
+    fn touch(&mut self, key: (u64, usize)) {
+        if let Some(i) = self.order.iter().position(|k| *k == key) {
+            self.order.remove(i);
+        }
+        self.order.push_back(key);
+    }
+    pub fn get(&mut self, key: (u64, usize)) -> Option<Arc<Vec<u8>>> {
+        let data = self.entries.get(&key)?.clone();
+        self.touch(key);
+        Some(data)
+    }
+    pub fn insert(&mut self, key: (u64, usize), data: Arc<Vec<u8>>) {
+        if data.len() > self.budget {
+            return;
+        }
+        if let Some(old) = self.entries.insert(key, data.clone()) {
+            self.used -= old.len();
+        }
+        self.used += data.len();
+        self.touch(key);
+        while self.used > self.budget {
+            match self.order.pop_front() {
+                Some(old) => {
+                    if let Some(old) = self.entries.remove(&old) {
+                        self.used -= old.len();
+                    }
+                },
+                None => break,
+            }
+        }
+    }
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+        self.used = 0;
+    }
+}
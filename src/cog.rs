@@ -0,0 +1,151 @@
+#![allow(dead_code)]
+
+use std::io::{Read, Seek, SeekFrom};
+use crate::{TiffError, Tiff, IFD};
+use crate::tiff::chunk_tags;
+use crate::ifd::{Baseline, Extension, TagID};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CogSeverity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CogRule {
+    // GDAL_STRUCTURAL_METADATA leader right after the header
+    GhostArea,
+    // every IFD before any image data
+    IfdsAtStart,
+    // main image and overviews tiled
+    Tiled,
+    // overviews by decreasing size
+    OverviewOrder,
+    // smallest overview data first, main image data last, tiles in increasing order
+    DataOrder,
+}
+
+#[derive(Debug, Clone)]
+pub struct CogFinding {
+    pub severity: CogSeverity,
+    pub rule: CogRule,
+    // index into the top level IFD chain
+    pub ifd: Option<usize>,
+    // file offset of the offending IFD or data
+    pub offset: Option<u64>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CogReport {
+    pub findings: Vec<CogFinding>,
+    // end of the ghost area, where the first IFD is expected
+    pub ghost_area_end: Option<u64>,
+}
+impl CogReport {
+    pub fn is_valid(&self) -> bool {
+        !self.findings.iter().any(|finding| finding.severity == CogSeverity::Error)
+    }
+    fn push(&mut self, severity: CogSeverity, rule: CogRule, ifd: Option<usize>, offset: Option<u64>, message: String) {
+        self.findings.push(CogFinding{severity, rule, ifd, offset, message});
+    }
+}
+
+// "GDAL_STRUCTURAL_METADATA_SIZE=000140 bytes\n" followed by that many bytes of metadata
+fn ghost_area_end<R: Read + Seek>(buffer: &mut R, start: u64) -> Result<Option<u64>, TiffError> {
+    const PREFIX: &[u8] = b"GDAL_STRUCTURAL_METADATA_SIZE=";
+    let mut leader = [0u8; 43];
+    buffer.seek(SeekFrom::Start(start)).or(Err(TiffError::UnexpectedEndOfBuffer))?;
+    if buffer.read_exact(&mut leader).is_err() || !leader.starts_with(PREFIX) {
+        return Ok(None);
+    }
+    let digits: Vec<u8> = leader[PREFIX.len()..].iter().copied().take_while(|b| b.is_ascii_digit()).collect();
+    let line_end = match leader.iter().position(|b| *b == b'\n') {
+        Some(end) => end as u64 + 1,
+        None => return Ok(None),
+    };
+    match std::str::from_utf8(&digits).ok().and_then(|size| size.parse::<u64>().ok()) {
+        Some(size) => Ok(Some(start + line_end + size)),
+        None => Ok(None),
+    }
+}
+
+// (offset, byte count) of every tile or strip
+fn chunks(tiff: &Tiff, ifd: &IFD) -> Result<Vec<(u64, u64)>, TiffError> {
+    let (offsets, byte_counts) = chunk_tags(ifd);
+    let offsets = tiff.load_tag(ifd.get_tag(offsets)?)?.as_unsigned_integers()?;
+    let byte_counts = tiff.load_tag(ifd.get_tag(byte_counts)?)?.as_unsigned_integers()?;
+    Ok(offsets.into_iter().zip(byte_counts).collect())
+}
+
+// Checks the Cloud Optimized GeoTIFF layout rules, as GDAL's validator does.
+pub fn validate_cog(tiff: &Tiff) -> Result<CogReport, TiffError> {
+    let mut report = CogReport::default();
+    let header_size = if tiff.header.is_btf() { 16 } else { 8 };
+    report.ghost_area_end = tiff.with_reader(|mut buffer| ghost_area_end(&mut buffer, header_size))?;
+    let ifd_start = report.ghost_area_end.unwrap_or(header_size);
+    if report.ghost_area_end.is_none() {
+        report.push(CogSeverity::Warning, CogRule::GhostArea, None, Some(header_size),
+            String::from("no GDAL_STRUCTURAL_METADATA ghost area after the header"));
+    }
+    if tiff.header.first_ifd != ifd_start {
+        report.push(CogSeverity::Error, CogRule::IfdsAtStart, Some(0), Some(tiff.header.first_ifd),
+            format!("first IFD at {} instead of {}", tiff.header.first_ifd, ifd_start));
+    }
+
+    let mut images: Vec<(IFD, Vec<(u64, u64)>)> = vec![];
+    for index in 0..tiff.len() {
+        let ifd = tiff.read_frame(index)?;
+        let chunks = chunks(tiff, &ifd)?;
+        images.push((ifd, chunks));
+    }
+    // sparse chunks (offset or byte count 0) have no data
+    let data_start = images.iter()
+        .flat_map(|(_, chunks)| chunks.iter())
+        .filter(|(offset, byte_count)| *offset > 0 && *byte_count > 0)
+        .map(|(offset, _)| *offset)
+        .min();
+
+    let mut previous: Option<(u64, u64)> = None;
+    for (index, (ifd, chunks)) in images.iter().enumerate() {
+        let (index, pos) = (Some(index), Some(ifd.pos));
+        if let Some(data_start) = data_start {
+            if ifd.pos > data_start {
+                report.push(CogSeverity::Error, CogRule::IfdsAtStart, index, pos,
+                    format!("IFD at {} is after image data starting at {}", ifd.pos, data_start));
+            }
+        }
+        let (width, height) = (ifd.width()?, ifd.height()?);
+        // GDAL accepts small untiled main images
+        if !ifd.is_tiled() && (index != Some(0) || width > 512 || height > 512) {
+            report.push(CogSeverity::Error, CogRule::Tiled, index, pos,
+                format!("{}x{} image is not tiled", width, height));
+        }
+        let chunks: Vec<&(u64, u64)> = chunks.iter().filter(|(offset, byte_count)| *offset > 0 && *byte_count > 0).collect();
+        for pair in chunks.windows(2) {
+            if pair[1].0 < pair[0].0 {
+                report.push(CogSeverity::Error, CogRule::DataOrder, index, Some(pair[1].0),
+                    format!("chunk data at {} comes before the previous chunk at {}", pair[1].0, pair[0].0));
+                break;
+            }
+        }
+        if ifd.is_mask() {
+            continue;
+        }
+        let first = chunks.first().map(|(offset, _)| *offset);
+        if let Some((previous_width, previous_first)) = previous {
+            if width >= previous_width {
+                report.push(CogSeverity::Error, CogRule::OverviewOrder, index, pos,
+                    format!("overview width {} is not smaller than {}", width, previous_width));
+            }
+            if let Some(first) = first {
+                if first > previous_first {
+                    report.push(CogSeverity::Error, CogRule::DataOrder, index, Some(first),
+                        format!("overview data at {} comes after the data of the larger level at {}", first, previous_first));
+                }
+            }
+        }
+        previous = Some((width, first.unwrap_or(u64::MAX)));
+    }
+    Ok(report)
+}
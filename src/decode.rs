@@ -0,0 +1,172 @@
+#![allow(dead_code)]
+
+use std::io::Read;
+use crate::{TiffError, CompressionScheme, FillOrder, Photometric, Predictor, IFD};
+use crate::transform::{apply_fill_order, invert_samples, fill_default_color};
+
+// Everything needed to turn the stored bytes of a strip or tile of one IFD into
+// uncompressed sample data. It holds no reader, so chunks can be decoded on any thread.
+#[derive(Debug)]
+pub struct ChunkDecoder {
+    compression: CompressionScheme,
+    fill_order: FillOrder,
+    predictor: Predictor,
+    bpp: Vec<u64>,
+    chunk_width: usize,
+    le: bool,
+    // WhiteIsZero unsigned samples are returned as BlackIsZero
+    invert: bool,
+    // decoded contents of a chunk absent from the file: DefaultImageColor, or zeros
+    empty_chunk: Vec<u8>,
+}
+impl ChunkDecoder {
+    pub fn from_ifd(ifd: &IFD, le: bool) -> Result<Self, TiffError> {
+        let bpp = ifd.bpp()?;
+        let (chunk_width, chunk_height, _) = ifd.chunk_layout()?;
+        let invert = matches!(ifd.photometric(), Ok(Photometric::WhiteIsZero)) && ifd.sample_format()? == 1;
+        let empty_chunk = Self::empty_chunk(ifd, &bpp, chunk_width, chunk_height, le)?;
+        Ok(Self{
+            compression: ifd.compression()?,
+            fill_order: ifd.fill_order()?,
+            predictor: ifd.predictor()?,
+            bpp,
+            chunk_width: chunk_width as usize,
+            le,
+            invert,
+            empty_chunk,
+        })
+    }
+    fn empty_chunk(ifd: &IFD, bpp: &[u64], chunk_width: u64, chunk_height: u64, le: bool) -> Result<Vec<u8>, TiffError> {
+        let bits: u64 = bpp.iter().sum();
+        let mut data = vec![0u8; (chunk_height * (chunk_width * bits).div_ceil(8)) as usize];
+        let color = match ifd.default_image_color() {
+            Ok(color) if color.len() == bpp.len() && bpp.iter().all(|b| (8..=64).contains(b) && b.is_multiple_of(8)) => color,
+            _ => return Ok(data),
+        };
+        // one pixel of samples in the file byte order, like the decoded data
+        let mut pixel: Vec<u8> = vec![];
+        for (value, bits) in color.iter().zip(bpp) {
+            let size = (*bits / 8) as usize;
+            if le {
+                pixel.extend(&value.to_le_bytes()[..size]);
+            } else {
+                pixel.extend(&value.to_be_bytes()[8 - size..]);
+            }
+        }
+        let pixels = data.len() - data.len() % pixel.len();
+        fill_default_color(&mut data[..pixels], &pixel)?;
+        Ok(data)
+    }
+    pub fn decode(&self, mut data: Vec<u8>) -> Result<Vec<u8>, TiffError> {
+        let mut data = if data.is_empty() {
+            // sparse chunk, absent from the file
+            self.empty_chunk.clone()
+        } else {
+            apply_fill_order(&mut data, &self.fill_order);
+            let mut data = match self.compression {
+                CompressionScheme::NoCompression => data,
+                CompressionScheme::LZW => decode_lzw(&data)?,
+                CompressionScheme::AdobeDeflate | CompressionScheme::Deflate => decode_deflate(&data)?,
+                CompressionScheme::PackBits => decode_packbits(&data)?,
+                _ => return Err(TiffError::NotSupportedCompressionScheme),
+            };
+            match self.predictor {
+                Predictor::NoPrediction => (),
+                Predictor::Horizontal => self.reverse_horizontal(&mut data)?,
+                Predictor::FloatingPoint => return Err(TiffError::NotSupportedPredictor),
+            }
+            data
+        };
+        if self.invert {
+            invert_samples(&mut data);
+        }
+        Ok(data)
+    }
+    fn reverse_horizontal(&self, data: &mut [u8]) -> Result<(), TiffError> {
+        let bits = self.bpp.first().copied().unwrap_or(8);
+        if self.bpp.iter().any(|b| *b != bits) {
+            return Err(TiffError::NotSupportedPredictor);
+        }
+        let samples = self.bpp.len();
+        let size = match bits {
+            8 | 16 | 32 | 64 => bits as usize / 8,
+            _ => return Err(TiffError::NotSupportedPredictor),
+        };
+        let stride = self.chunk_width * samples * size;
+        if stride == 0 {
+            return Ok(());
+        }
+        for row in data.chunks_mut(stride) {
+            for i in (samples * size..row.len() - row.len() % size).step_by(size) {
+                let (left, right) = row.split_at_mut(i);
+                let prev = &left[i - samples * size..i - samples * size + size];
+                let cur = &mut right[..size];
+                // add with carry in the stored byte order
+                let mut carry = 0u16;
+                for k in 0..size {
+                    let j = if self.le { k } else { size - 1 - k };
+                    let sum = cur[j] as u16 + prev[j] as u16 + carry;
+                    cur[j] = sum as u8;
+                    carry = sum >> 8;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// TIFF 6.0 LZW: most significant bit first, with the early code size switch.
+fn decode_lzw(data: &[u8]) -> Result<Vec<u8>, TiffError> {
+    let mut out: Vec<u8> = vec![];
+    let mut decoder = weezl::decode::Decoder::with_tiff_size_switch(weezl::BitOrder::Msb, 8);
+    // writers do not always end the stream with an EOI code
+    decoder.into_vec(&mut out).decode(data).status.or(Err(TiffError::InvalidCompressedData))?;
+    Ok(out)
+}
+
+// zlib stream, for both the Adobe (8) and the older PKZIP (32946) code.
+fn decode_deflate(data: &[u8]) -> Result<Vec<u8>, TiffError> {
+    let mut out: Vec<u8> = vec![];
+    flate2::read::ZlibDecoder::new(data).read_to_end(&mut out).or(Err(TiffError::InvalidCompressedData))?;
+    Ok(out)
+}
+
+fn decode_packbits(data: &[u8]) -> Result<Vec<u8>, TiffError> {
+    let mut out: Vec<u8> = vec![];
+    let mut i = 0;
+    while i < data.len() {
+        let n = data[i] as i8;
+        i += 1;
+        match n {
+            // literal run of n + 1 bytes
+            0..=127 => {
+                let run = data.get(i..i + n as usize + 1).ok_or(TiffError::InvalidCompressedData)?;
+                out.extend(run);
+                i += run.len();
+            },
+            // no-op
+            -128 => (),
+            // next byte repeated 1 - n times
+            _ => {
+                let byte = *data.get(i).ok_or(TiffError::InvalidCompressedData)?;
+                out.extend(std::iter::repeat_n(byte, (1 - n as isize) as usize));
+                i += 1;
+            },
+        }
+    }
+    Ok(out)
+}
+
+// Decodes independent chunks, across the rayon thread pool when the
+// "parallel" feature is enabled.
+pub fn decode_chunks(decoder: &ChunkDecoder, chunks: Vec<Vec<u8>>) -> Result<Vec<Vec<u8>>, TiffError> {
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+        chunks.into_par_iter().map(|data| decoder.decode(data)).collect()
+    }
+    #[cfg(not(feature = "parallel"))]
+    {
+        chunks.into_iter().map(|data| decoder.decode(data)).collect()
+    }
+}
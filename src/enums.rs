@@ -2,7 +2,102 @@
 
 build_integer_enum!(CompressionScheme, u64,
     NoCompression, 1,
+    CCITTRLE,      2,
+    CCITTFax3,     3,
+    CCITTFax4,     4,
     LZW,           5,
+    OldJPEG,       6,
+    JPEG,          7,
     AdobeDeflate,  8,
     PackBits,      32773,
     Deflate,       32946);
+
+build_integer_enum!(Orientation, u64,
+    TopLeft,     1,
+    TopRight,    2,
+    BottomRight, 3,
+    BottomLeft,  4,
+    LeftTop,     5,
+    RightTop,    6,
+    RightBottom, 7,
+    LeftBottom,  8);
+
+build_integer_enum!(FillOrder, u64,
+    MsbToLsb, 1,
+    LsbToMsb, 2);
+
+build_integer_enum!(Photometric, u64,
+    WhiteIsZero,      0,
+    BlackIsZero,      1,
+    RGB,              2,
+    Palette,          3,
+    TransparencyMask, 4,
+    Separated,        5,
+    YCbCr,            6,
+    CIELab,           8,
+    ICCLab,           9,
+    ITULab,           10,
+    LogL,             32844,
+    LogLuv,           32845);
+
+build_integer_enum!(Predictor, u64,
+    NoPrediction,  1,
+    Horizontal,    2,
+    FloatingPoint, 3);
+
+build_integer_enum!(ModelType, u64,
+    Projected,  1,
+    Geographic, 2,
+    Geocentric, 3);
+
+build_integer_enum!(RasterType, u64,
+    PixelIsArea,  1,
+    PixelIsPoint, 2);
+
+build_integer_enum!(GeoKeyId, u64,
+    GTModelType,                  1024,
+    GTRasterType,                 1025,
+    GTCitation,                   1026,
+    GeographicType,               2048,
+    GeogCitation,                 2049,
+    GeogGeodeticDatum,            2050,
+    GeogPrimeMeridian,            2051,
+    GeogLinearUnits,              2052,
+    GeogLinearUnitSize,           2053,
+    GeogAngularUnits,             2054,
+    GeogAngularUnitSize,          2055,
+    GeogEllipsoid,                2056,
+    GeogSemiMajorAxis,            2057,
+    GeogSemiMinorAxis,            2058,
+    GeogInvFlattening,            2059,
+    GeogAzimuthUnits,             2060,
+    GeogPrimeMeridianLong,        2061,
+    GeogTOWGS84,                  2062,
+    ProjectedCSType,              3072,
+    PCSCitation,                  3073,
+    Projection,                   3074,
+    ProjCoordTrans,               3075,
+    ProjLinearUnits,              3076,
+    ProjLinearUnitSize,           3077,
+    ProjStdParallel1,             3078,
+    ProjStdParallel2,             3079,
+    ProjNatOriginLong,            3080,
+    ProjNatOriginLat,             3081,
+    ProjFalseEasting,             3082,
+    ProjFalseNorthing,            3083,
+    ProjFalseOriginLong,          3084,
+    ProjFalseOriginLat,           3085,
+    ProjFalseOriginEasting,       3086,
+    ProjFalseOriginNorthing,      3087,
+    ProjCenterLong,               3088,
+    ProjCenterLat,                3089,
+    ProjCenterEasting,            3090,
+    ProjCenterNorthing,           3091,
+    ProjScaleAtNatOrigin,         3092,
+    ProjScaleAtCenter,            3093,
+    ProjAzimuthAngle,             3094,
+    ProjStraightVertPoleLong,     3095,
+    VerticalCSType,               4096,
+    VerticalCitation,             4097,
+    VerticalDatum,                4098,
+    VerticalUnits,                4099);
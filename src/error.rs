@@ -15,4 +15,18 @@ pub enum TiffError {
     IncompatibleTagDataKind,
     CannotFindTag,
     NotSupportedCompressionScheme,
+    NotSupportedOrientation,
+    NotSupportedFillOrder,
+    NotSupportedPhotometric,
+    UnexpectedBufferSize,
+    CannotFetchRange,
+    TagDataNotLoaded,
+    NotSupportedSampleLayout,
+    NotSupportedPredictor,
+    InvalidGeoKeyDirectory,
+    InvalidGeoTransform,
+    InvalidGdalMetadata,
+    NoReader,
+    CircularIFDChain,
+    InvalidCompressedData,
 }
@@ -0,0 +1,189 @@
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use crate::TiffError;
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+// name="value" pairs of an XML start tag
+fn attributes(tag: &str) -> HashMap<String, String> {
+    let mut map = HashMap::new();
+    let mut rest = tag;
+    while let Some(eq) = rest.find('=') {
+        let name = rest[..eq].trim().to_string();
+        let value = rest[eq + 1..].trim_start();
+        let quote = match value.chars().next() {
+            Some(quote) if quote == '"' || quote == '\'' => quote,
+            _ => break,
+        };
+        let end = match value[1..].find(quote) {
+            Some(end) => end + 1,
+            None => break,
+        };
+        map.insert(name, unescape(&value[1..end]));
+        rest = &value[end + 1..];
+    }
+    map
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GdalMetadataItem {
+    pub name: String,
+    pub value: String,
+    pub domain: Option<String>,
+    // band index from 0, None for dataset items
+    pub sample: Option<usize>,
+    // e.g. "scale", "offset", "description", "unittype"
+    pub role: Option<String>,
+}
+
+// GDAL_METADATA (42112): <GDALMetadata><Item name=".." sample=".." role="..">value</Item>...</GDALMetadata>
+#[derive(Debug, Clone, Default)]
+pub struct GdalMetadata {
+    pub items: Vec<GdalMetadataItem>,
+}
+impl GdalMetadata {
+    pub fn from_xml(xml: &str) -> Result<Self, TiffError> {
+        if !xml.trim_start().starts_with("<GDALMetadata") {
+            return Err(TiffError::InvalidGdalMetadata);
+        }
+        let mut items: Vec<GdalMetadataItem> = vec![];
+        let mut rest = xml;
+        while let Some(start) = rest.find("<Item") {
+            rest = &rest[start + 5..];
+            let close = rest.find('>').ok_or(TiffError::InvalidGdalMetadata)?;
+            let self_closing = rest[..close].ends_with('/');
+            let mut attributes = attributes(rest[..close].trim_end_matches('/'));
+            rest = &rest[close + 1..];
+            let value = if self_closing {
+                String::new()
+            } else {
+                let end = rest.find("</Item>").ok_or(TiffError::InvalidGdalMetadata)?;
+                let value = unescape(&rest[..end]);
+                rest = &rest[end + 7..];
+                value
+            };
+            let sample = match attributes.remove("sample") {
+                Some(sample) => Some(sample.parse().or(Err(TiffError::InvalidGdalMetadata))?),
+                None => None,
+            };
+            items.push(GdalMetadataItem{
+                name: attributes.remove("name").ok_or(TiffError::InvalidGdalMetadata)?,
+                value,
+                domain: attributes.remove("domain"),
+                sample,
+                role: attributes.remove("role"),
+            });
+        }
+        Ok(Self{items})
+    }
+    // default domain name/value pairs of the dataset (sample None) or of one band
+    fn map(&self, sample: Option<usize>) -> HashMap<String, String> {
+        self.items.iter()
+            .filter(|item| item.sample == sample && item.domain.is_none() && item.role.is_none())
+            .map(|item| (item.name.clone(), item.value.clone()))
+            .collect()
+    }
+    pub fn dataset(&self) -> HashMap<String, String> {
+        self.map(None)
+    }
+    pub fn band(&self, sample: usize) -> HashMap<String, String> {
+        self.map(Some(sample))
+    }
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.items.iter()
+            .find(|item| item.name == name && item.sample.is_none() && item.domain.is_none())
+            .map(|item| item.value.as_str())
+    }
+    fn role(&self, sample: usize, role: &str) -> Option<f64> {
+        self.items.iter()
+            .find(|item| item.sample == Some(sample) && item.role.as_deref() == Some(role))
+            .and_then(|item| item.value.trim().parse().ok())
+    }
+    // physical value = raw * scale + offset
+    pub fn scale(&self, sample: usize) -> Option<f64> {
+        self.role(sample, "scale")
+    }
+    pub fn offset(&self, sample: usize) -> Option<f64> {
+        self.role(sample, "offset")
+    }
+}
+
+// GDAL_NODATA (42113), typed after SampleFormat
+#[derive(Debug, Clone, PartialEq)]
+pub enum NoData {
+    Unsigned(u64),
+    Signed(i64),
+    Float(f64),
+}
+impl NoData {
+    // `sample_format`: 1 unsigned, 2 signed, 3 IEEE float
+    pub fn parse(text: &str, sample_format: u64) -> Result<Self, TiffError> {
+        let text = text.trim_end_matches('\0').trim();
+        let value: f64 = match text.to_ascii_lowercase().as_str() {
+            "nan" => f64::NAN,
+            "inf" | "+inf" => f64::INFINITY,
+            "-inf" => f64::NEG_INFINITY,
+            _ => text.parse().or(Err(TiffError::IncompatibleTagDataKind))?,
+        };
+        let integral = value.fract() == 0.0;
+        match sample_format {
+            1 if integral && value >= 0.0 && value <= u64::MAX as f64 => Ok(Self::Unsigned(value as u64)),
+            2 if integral && value >= i64::MIN as f64 && value <= i64::MAX as f64 => Ok(Self::Signed(value as i64)),
+            3 => Ok(Self::Float(value)),
+            _ => Err(TiffError::IncompatibleTagDataKind),
+        }
+    }
+    fn matches(&self, sample: &[u8], le: bool) -> bool {
+        let mut bytes = [0u8; 8];
+        let n = sample.len();
+        if le {
+            bytes[..n].copy_from_slice(sample);
+        } else {
+            for (i, byte) in sample.iter().rev().enumerate() {
+                bytes[i] = *byte;
+            }
+        }
+        let raw = u64::from_le_bytes(bytes);
+        match self {
+            Self::Unsigned(value) => raw == *value,
+            // sign extend from the sample width
+            Self::Signed(value) => ((raw << (64 - 8 * n)) as i64) >> (64 - 8 * n) == *value,
+            // compared in f32 for Float32 samples: 0.1 never equals a widened 0.1f32
+            Self::Float(value) => match n {
+                4 => {
+                    let (sample, value) = (f32::from_bits(raw as u32), *value as f32);
+                    sample == value || (sample.is_nan() && value.is_nan())
+                },
+                _ => {
+                    let sample = f64::from_bits(raw);
+                    sample == *value || (sample.is_nan() && value.is_nan())
+                },
+            },
+        }
+    }
+    // One flag per pixel of decoded, interleaved samples in file byte order: false where
+    // every sample of the pixel is nodata.
+    pub fn mask(&self, data: &[u8], bits_per_sample: u64, samples: usize, le: bool) -> Result<Vec<bool>, TiffError> {
+        let size = (bits_per_sample / 8) as usize;
+        let valid_size = match self {
+            Self::Float(_) => size == 4 || size == 8,
+            _ => (1..=8).contains(&size),
+        };
+        if !bits_per_sample.is_multiple_of(8) || !valid_size || samples == 0 {
+            return Err(TiffError::NotSupportedSampleLayout);
+        }
+        if !data.len().is_multiple_of(size * samples) {
+            return Err(TiffError::UnexpectedBufferSize);
+        }
+        Ok(data.chunks_exact(size * samples)
+            .map(|pixel| !pixel.chunks_exact(size).all(|sample| self.matches(sample, le)))
+            .collect())
+    }
+}
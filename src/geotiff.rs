@@ -0,0 +1,224 @@
+#![allow(dead_code)]
+
+use crate::{TiffError, IFD, ModelType, RasterType, GeoKeyId};
+use crate::ifd::{GeoTiff, TagID};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GeoKeyValue {
+    Short(Vec<u16>),
+    Double(Vec<f64>),
+    Ascii(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct GeoKey {
+    pub id: u16,
+    pub value: GeoKeyValue,
+}
+impl GeoKey {
+    // None for keys outside the GeoTIFF 1.1 registry, e.g. private keys
+    pub fn key(&self) -> Option<GeoKeyId> {
+        GeoKeyId::from_number(self.id as u64)
+    }
+}
+
+// GeoKeyDirectory (34735) with every value resolved from the directory itself,
+// GeoDoubleParams (34736) or GeoAsciiParams (34737).
+#[derive(Debug, Clone)]
+pub struct GeoKeyDirectory {
+    pub version: u16,
+    pub revision: (u16, u16),
+    pub keys: Vec<GeoKey>,
+}
+impl GeoKeyDirectory {
+    pub fn from_ifd(ifd: &IFD) -> Result<Self, TiffError> {
+        let directory: Vec<u16> = ifd.get_tag(TagID::GeoTiffTag(GeoTiff::GeoKeyDirectory))?.data()?
+            .as_unsigned_integers()?.iter().map(|v| *v as u16).collect();
+        let doubles = match ifd.get_tag(TagID::GeoTiffTag(GeoTiff::GeoDoubleParams)) {
+            Ok(tag) => tag.data()?.as_floating_points()?,
+            Err(_) => vec![],
+        };
+        let ascii = match ifd.get_tag(TagID::GeoTiffTag(GeoTiff::GeoAsciiParams)) {
+            Ok(tag) => tag.data()?.as_string()?,
+            Err(_) => String::new(),
+        };
+        Self::from_params(&directory, &doubles, &ascii)
+    }
+    pub fn from_params(directory: &[u16], doubles: &[f64], ascii: &str) -> Result<Self, TiffError> {
+        // header: KeyDirectoryVersion, KeyRevision, MinorRevision, NumberOfKeys
+        let (header, entries) = match directory.split_first_chunk::<4>() {
+            Some(split) => split,
+            None => return Err(TiffError::InvalidGeoKeyDirectory),
+        };
+        let count = header[3] as usize;
+        if entries.len() < count * 4 {
+            return Err(TiffError::InvalidGeoKeyDirectory);
+        }
+        let mut keys: Vec<GeoKey> = vec![];
+        for entry in entries.chunks_exact(4).take(count) {
+            // KeyID, TIFFTagLocation, Count, Value_Offset
+            let (id, location, count, offset) = (entry[0], entry[1], entry[2] as usize, entry[3] as usize);
+            let range = offset..offset + count;
+            let value = match location {
+                0 => GeoKeyValue::Short(vec![entry[3]]),
+                34735 => GeoKeyValue::Short(directory.get(range).ok_or(TiffError::InvalidGeoKeyDirectory)?.to_vec()),
+                34736 => GeoKeyValue::Double(doubles.get(range).ok_or(TiffError::InvalidGeoKeyDirectory)?.to_vec()),
+                34737 => {
+                    // strings are terminated by '|' in place of NUL
+                    let text = ascii.get(range).ok_or(TiffError::InvalidGeoKeyDirectory)?;
+                    GeoKeyValue::Ascii(text.trim_end_matches('|').to_string())
+                },
+                _ => return Err(TiffError::InvalidGeoKeyDirectory),
+            };
+            keys.push(GeoKey{id, value});
+        }
+        Ok(Self{version: header[0], revision: (header[1], header[2]), keys})
+    }
+    pub fn get(&self, id: GeoKeyId) -> Result<&GeoKeyValue, TiffError> {
+        let id = id.to_number() as u16;
+        match self.keys.iter().find(|key| key.id == id) {
+            Some(key) => Ok(&key.value),
+            None => Err(TiffError::CannotFindTag),
+        }
+    }
+    pub fn get_short(&self, id: GeoKeyId) -> Result<u16, TiffError> {
+        match self.get(id)? {
+            GeoKeyValue::Short(values) if values.len() == 1 => Ok(values[0]),
+            _ => Err(TiffError::IncompatibleTagDataKind),
+        }
+    }
+    pub fn get_double(&self, id: GeoKeyId) -> Result<f64, TiffError> {
+        match self.get(id)? {
+            GeoKeyValue::Double(values) if values.len() == 1 => Ok(values[0]),
+            _ => Err(TiffError::IncompatibleTagDataKind),
+        }
+    }
+    pub fn get_ascii(&self, id: GeoKeyId) -> Result<&str, TiffError> {
+        match self.get(id)? {
+            GeoKeyValue::Ascii(value) => Ok(value),
+            _ => Err(TiffError::IncompatibleTagDataKind),
+        }
+    }
+    pub fn model_type(&self) -> Result<ModelType, TiffError> {
+        ModelType::from_number(self.get_short(GeoKeyId::GTModelType)? as u64).ok_or(TiffError::InvalidGeoKeyDirectory)
+    }
+    pub fn raster_type(&self) -> Result<RasterType, TiffError> {
+        // default: PixelIsArea
+        match self.get_short(GeoKeyId::GTRasterType) {
+            Ok(value) => RasterType::from_number(value as u64).ok_or(TiffError::InvalidGeoKeyDirectory),
+            Err(_) => Ok(RasterType::PixelIsArea),
+        }
+    }
+    pub fn projected_cs_type(&self) -> Result<u16, TiffError> {
+        self.get_short(GeoKeyId::ProjectedCSType)
+    }
+    pub fn geographic_type(&self) -> Result<u16, TiffError> {
+        self.get_short(GeoKeyId::GeographicType)
+    }
+    pub fn vertical_cs_type(&self) -> Result<u16, TiffError> {
+        self.get_short(GeoKeyId::VerticalCSType)
+    }
+    // EPSG code of the horizontal CRS: projected if present, else geographic.
+    // 32767 (user-defined) is not an EPSG code and is reported as missing.
+    pub fn epsg(&self) -> Result<u16, TiffError> {
+        let code = match self.projected_cs_type() {
+            Ok(code) => code,
+            Err(_) => self.geographic_type()?,
+        };
+        match code {
+            0 | 32767 => Err(TiffError::CannotFindTag),
+            code => Ok(code),
+        }
+    }
+    // EPSG unit of measure codes, e.g. 9001 (metre) and 9102 (degree)
+    pub fn linear_units(&self) -> Result<u16, TiffError> {
+        match self.get_short(GeoKeyId::ProjLinearUnits) {
+            Ok(value) => Ok(value),
+            Err(_) => self.get_short(GeoKeyId::GeogLinearUnits),
+        }
+    }
+    pub fn angular_units(&self) -> Result<u16, TiffError> {
+        self.get_short(GeoKeyId::GeogAngularUnits)
+    }
+    pub fn citation(&self) -> Result<&str, TiffError> {
+        self.get_ascii(GeoKeyId::GTCitation)
+    }
+    pub fn geog_citation(&self) -> Result<&str, TiffError> {
+        self.get_ascii(GeoKeyId::GeogCitation)
+    }
+    pub fn pcs_citation(&self) -> Result<&str, TiffError> {
+        self.get_ascii(GeoKeyId::PCSCitation)
+    }
+}
+
+// Affine raster to model transform in GDAL order, with (col, row) addressing pixel corners:
+// x = c[0] + col * c[1] + row * c[2]
+// y = c[3] + col * c[4] + row * c[5]
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeoTransform {
+    pub coefficients: [f64; 6],
+}
+impl GeoTransform {
+    // From ModelTransformation, or else a single tiepoint with ModelPixelScale. Raster
+    // coordinates of PixelIsPoint files refer to pixel centers and are shifted by half
+    // a pixel, so the result always uses the PixelIsArea convention.
+    pub fn from_ifd(ifd: &IFD) -> Result<Self, TiffError> {
+        let transform = match ifd.model_transformation() {
+            Ok(matrix) => Self::from_matrix(&matrix)?,
+            Err(_) => Self::from_tiepoint(&ifd.model_tiepoints()?, &ifd.model_pixel_scale()?)?,
+        };
+        let raster_type = match ifd.geo_keys() {
+            Ok(keys) => keys.raster_type()?,
+            Err(_) => RasterType::PixelIsArea,
+        };
+        match raster_type {
+            RasterType::PixelIsPoint => Ok(transform.shifted(-0.5, -0.5)),
+            RasterType::PixelIsArea => Ok(transform),
+        }
+    }
+    // 4x4 row-major matrix; only the 2D part is used
+    pub fn from_matrix(matrix: &[f64]) -> Result<Self, TiffError> {
+        if matrix.len() != 16 {
+            return Err(TiffError::InvalidGeoTransform);
+        }
+        Ok(Self{coefficients: [matrix[3], matrix[0], matrix[1], matrix[7], matrix[4], matrix[5]]})
+    }
+    // Several tiepoints without a scale are ground control points, which are not an affine transform.
+    pub fn from_tiepoint(tiepoints: &[f64], scale: &[f64]) -> Result<Self, TiffError> {
+        if tiepoints.len() < 6 || scale.len() < 2 {
+            return Err(TiffError::InvalidGeoTransform);
+        }
+        let (i, j, x, y) = (tiepoints[0], tiepoints[1], tiepoints[3], tiepoints[4]);
+        let (scale_x, scale_y) = (scale[0], scale[1]);
+        Ok(Self{coefficients: [x - i * scale_x, scale_x, 0.0, y + j * scale_y, 0.0, -scale_y]})
+    }
+    // The same mapping for raster coordinates offset by (dx, dy) pixels.
+    pub fn shifted(&self, dx: f64, dy: f64) -> Self {
+        let (x, y) = self.pixel_to_model(dx, dy);
+        let c = self.coefficients;
+        Self{coefficients: [x, c[1], c[2], y, c[4], c[5]]}
+    }
+    pub fn pixel_to_model(&self, col: f64, row: f64) -> (f64, f64) {
+        let c = self.coefficients;
+        (c[0] + col * c[1] + row * c[2], c[3] + col * c[4] + row * c[5])
+    }
+    pub fn model_to_pixel(&self, x: f64, y: f64) -> Result<(f64, f64), TiffError> {
+        let c = self.coefficients;
+        let det = c[1] * c[5] - c[2] * c[4];
+        if det == 0.0 || !det.is_finite() {
+            return Err(TiffError::InvalidGeoTransform);
+        }
+        let (dx, dy) = (x - c[0], y - c[3]);
+        Ok(((dx * c[5] - dy * c[2]) / det, (dy * c[1] - dx * c[4]) / det))
+    }
+    // (min_x, min_y, max_x, max_y) of the four image corners in model coordinates
+    pub fn bounds(&self, width: u64, height: u64) -> (f64, f64, f64, f64) {
+        let (w, h) = (width as f64, height as f64);
+        let corners = [(0.0, 0.0), (w, 0.0), (0.0, h), (w, h)].map(|(col, row)| self.pixel_to_model(col, row));
+        let mut bounds = (f64::INFINITY, f64::INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY);
+        for (x, y) in corners {
+            bounds = (bounds.0.min(x), bounds.1.min(y), bounds.2.max(x), bounds.3.max(y));
+        }
+        bounds
+    }
+}
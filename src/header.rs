@@ -1,7 +1,6 @@
 #![allow(dead_code)]
 
-use std::fs::File;
-use std::io::{BufReader, Read};
+use std::io::{Read, Seek};
 use crate::TiffError;
 
 
@@ -12,7 +11,7 @@ pub struct Header {
     pub first_ifd:  u64,
 }
 impl Header {
-    pub fn from_buffer(buffer: &mut BufReader<File>) -> Result<Self, TiffError> {
+    pub fn from_buffer<R: Read + Seek>(buffer: &mut R) -> Result<Self, TiffError> {
         let byte_order = buffer_as!(buffer, u16, true)?;
         let le = match byte_order {
             0x4949 => true,
@@ -21,9 +20,8 @@ impl Header {
         };
 
         let version = buffer_as!(buffer, u16, le)?;
-        let first_ifd: u64;
-        match version {
-            42 => first_ifd = buffer_as!(buffer, u32, le)? as u64,
+        let first_ifd = match version {
+            42 => buffer_as!(buffer, u32, le)? as u64,
             43 => {
                 let offset_size = buffer_as!(buffer, u16, le)?;
                 if offset_size != 8 {
@@ -33,10 +31,10 @@ impl Header {
                 if reserved != 0 {
                     return Err(TiffError::UnexpectedHeaderReserved);
                 }
-                first_ifd = buffer_as!(buffer, u64, le)?;
+                buffer_as!(buffer, u64, le)?
             },
             _ => return Err(TiffError::UnexpectedHeaderVersion),
-        }
+        };
 
         Ok(Self{byte_order, version, first_ifd})
     }
@@ -1,8 +1,9 @@
 #![allow(dead_code)]
 
-use std::fs::File;
-use std::io::{BufReader, Seek, SeekFrom, Read};
-use crate::{TiffError, CompressionScheme};
+use std::collections::HashSet;
+use std::io::{Seek, SeekFrom, Read};
+use std::sync::OnceLock;
+use crate::{TiffError, CompressionScheme, Orientation, FillOrder, Photometric, Predictor, GeoKeyDirectory, GeoTransform, GdalMetadata, NoData};
 
 fn array_from_slice<const N: usize>(slice: &[u8]) -> &[u8; N] {
     <&[u8] as std::convert::TryInto<&[u8; N]>>::try_into(slice).unwrap()
@@ -131,15 +132,29 @@ macro_rules! define_tag_data {
                     _ => Err(TiffError::UnknownTagKind),
                 }
             }
-            pub fn from_buffer(buffer: &mut BufReader<File>, btf: bool, le: bool) -> Result<Self, TiffError> {
+            pub fn size_of_kind(kind: u16) -> Result<usize, TiffError> {
+                match kind {
+                    $($kind => Ok($size)),*,
+                    _ => Err(TiffError::UnknownTagKind),
+                }
+            }
+            pub fn from_bytes(kind: u16, count: usize, data: &[u8], le: bool) -> Result<Self, TiffError> {
+                let mut buffer = std::io::Cursor::new(data);
+                match kind {
+                    $($kind => {
+                        let mut values = vec![<$typ as Default>::default(); count];
+                        for value in values.iter_mut() {
+                            *value = buffer_as!(buffer, $typ, le)?;
+                        }
+                        Ok(Self::$name(values))
+                    }),*,
+                    _ => Err(TiffError::UnknownTagKind),
+                }
+            }
+            pub fn from_buffer<R: Read + Seek>(buffer: &mut R, btf: bool, le: bool) -> Result<Self, TiffError> {
                 let kind = buffer_as!(buffer, u16, le)?;
                 let count = buffer_as_offset!(buffer, btf, le) as usize;
-                let byte_count = count * {
-                    match kind {
-                        $($kind => $size),*,
-                        _ => return Err(TiffError::UnknownTagKind),
-                    }
-                };
+                let byte_count = count * Self::size_of_kind(kind)?;
 
                 let pos = buffer.stream_position().or(Err(TiffError::UnknownBufferError))?;
 
@@ -248,9 +263,22 @@ define_tag_data!(
     SLong8,    i64,     17,     8,
     IFD8,      u64,     18,     8
 );
+impl TagData {
+    // ASCII (or byte) values as text, without the trailing NUL terminator(s).
+    pub fn as_string(&self) -> Result<String, TiffError> {
+        match self {
+            Self::Ascii(values) | Self::Byte(values) | Self::Undefined(values) => {
+                Ok(String::from_utf8_lossy(values).trim_end_matches('\0').to_string())
+            },
+            _ => Err(TiffError::IncompatibleTagDataKind),
+        }
+    }
+}
 macro_rules! define_tag_id {
     ($category:ident, $($id:expr, $name:ident),*) => {
-        #[derive(PartialEq)]
+        // variants keep the spec's tag names, e.g. Exif::ExifIFD
+        #[derive(Clone, PartialEq)]
+        #[allow(clippy::enum_variant_names)]
         pub enum $category {
             $($name),*
         }
@@ -376,11 +404,142 @@ define_tag_id!(Extension,
 0x800D, ImageID,
 0x87AC, ImageLayer
 );
-#[derive(PartialEq)]
+define_tag_id!(Exif,
+0x829A, ExposureTime,
+0x829D, FNumber,
+0x8769, ExifIFD,
+0x8825, GPSInfo,
+0x8822, ExposureProgram,
+0x8824, SpectralSensitivity,
+0x8827, ISOSpeedRatings,
+0x8828, OECF,
+0x8830, SensitivityType,
+0x9000, ExifVersion,
+0x9003, DateTimeOriginal,
+0x9004, DateTimeDigitized,
+0x9010, OffsetTime,
+0x9011, OffsetTimeOriginal,
+0x9012, OffsetTimeDigitized,
+0x9101, ComponentsConfiguration,
+0x9102, CompressedBitsPerPixel,
+0x9201, ShutterSpeedValue,
+0x9202, ApertureValue,
+0x9203, BrightnessValue,
+0x9204, ExposureBiasValue,
+0x9205, MaxApertureValue,
+0x9206, SubjectDistance,
+0x9207, MeteringMode,
+0x9208, LightSource,
+0x9209, Flash,
+0x920A, FocalLength,
+0x9214, SubjectArea,
+0x927C, MakerNote,
+0x9286, UserComment,
+0x9290, SubSecTime,
+0x9291, SubSecTimeOriginal,
+0x9292, SubSecTimeDigitized,
+0xA000, FlashpixVersion,
+0xA001, ColorSpace,
+0xA002, PixelXDimension,
+0xA003, PixelYDimension,
+0xA004, RelatedSoundFile,
+0xA005, InteroperabilityIFD,
+0xA20B, FlashEnergy,
+0xA20E, FocalPlaneXResolution,
+0xA20F, FocalPlaneYResolution,
+0xA210, FocalPlaneResolutionUnit,
+0xA214, SubjectLocation,
+0xA215, ExposureIndex,
+0xA217, SensingMethod,
+0xA300, FileSource,
+0xA301, SceneType,
+0xA302, CFAPattern,
+0xA401, CustomRendered,
+0xA402, ExposureMode,
+0xA403, WhiteBalance,
+0xA404, DigitalZoomRatio,
+0xA405, FocalLengthIn35mmFilm,
+0xA406, SceneCaptureType,
+0xA407, GainControl,
+0xA408, Contrast,
+0xA409, Saturation,
+0xA40A, Sharpness,
+0xA40C, SubjectDistanceRange,
+0xA420, ImageUniqueID,
+0xA430, CameraOwnerName,
+0xA431, BodySerialNumber,
+0xA432, LensSpecification,
+0xA433, LensMake,
+0xA434, LensModel,
+0xA435, LensSerialNumber
+);
+// Tags of the GPS IFD; their numbers overlap nothing else, so they are only
+// recognised inside an IFD reached through Exif::GPSInfo.
+define_tag_id!(Gps,
+0x0000, VersionID,
+0x0001, LatitudeRef,
+0x0002, Latitude,
+0x0003, LongitudeRef,
+0x0004, Longitude,
+0x0005, AltitudeRef,
+0x0006, Altitude,
+0x0007, TimeStamp,
+0x0008, Satellites,
+0x0009, Status,
+0x000A, MeasureMode,
+0x000B, DOP,
+0x000C, SpeedRef,
+0x000D, Speed,
+0x000E, TrackRef,
+0x000F, Track,
+0x0010, ImgDirectionRef,
+0x0011, ImgDirection,
+0x0012, MapDatum,
+0x0013, DestLatitudeRef,
+0x0014, DestLatitude,
+0x0015, DestLongitudeRef,
+0x0016, DestLongitude,
+0x0017, DestBearingRef,
+0x0018, DestBearing,
+0x0019, DestDistanceRef,
+0x001A, DestDistance,
+0x001B, ProcessingMethod,
+0x001C, AreaInformation,
+0x001D, DateStamp,
+0x001E, Differential,
+0x001F, HPositioningError
+);
+// Tags of the Interoperability IFD, reached through Exif::InteroperabilityIFD.
+define_tag_id!(Interop,
+0x0001, InteroperabilityIndex,
+0x0002, InteroperabilityVersion,
+0x1000, RelatedImageFileFormat,
+0x1001, RelatedImageWidth,
+0x1002, RelatedImageLength
+);
+define_tag_id!(GeoTiff,
+0x830E, ModelPixelScale,
+0x8482, ModelTiepoint,
+0x85D8, ModelTransformation,
+0x87AF, GeoKeyDirectory,
+0x87B0, GeoDoubleParams,
+0x87B1, GeoAsciiParams
+);
+define_tag_id!(Gdal,
+0xA480, GDALMetadata,
+0xA481, GDALNoData
+);
+#[derive(Clone, PartialEq)]
+#[allow(clippy::enum_variant_names)]
 pub enum TagID {
     PrivateTag(u16),
     BaselineTag(Baseline),
     ExtensionTag(Extension),
+    ExifTag(Exif),
+    GpsTag(Gps),
+    InteropTag(Interop),
+    GeoTiffTag(GeoTiff),
+    GdalTag(Gdal),
 }
 impl Default for TagID {
     fn default() -> Self {
@@ -393,6 +552,11 @@ impl std::fmt::Debug for TagID {
             Self::PrivateTag(id) => f.write_fmt(format_args!("PrivateTag({:#06x})", id)),
             Self::BaselineTag(id) => f.write_fmt(format_args!("{:#?}", id)),
             Self::ExtensionTag(id) => f.write_fmt(format_args!("{:#?}", id)),
+            Self::ExifTag(id) => f.write_fmt(format_args!("{:#?}", id)),
+            Self::GpsTag(id) => f.write_fmt(format_args!("{:#?}", id)),
+            Self::InteropTag(id) => f.write_fmt(format_args!("{:#?}", id)),
+            Self::GeoTiffTag(id) => f.write_fmt(format_args!("{:#?}", id)),
+            Self::GdalTag(id) => f.write_fmt(format_args!("{:#?}", id)),
         }
     }
 }
@@ -404,62 +568,340 @@ impl TagID {
         if let Ok(value) = Extension::from_u16(n) {
             return Self::ExtensionTag(value);
         }
+        if let Ok(value) = Exif::from_u16(n) {
+            return Self::ExifTag(value);
+        }
+        if let Ok(value) = GeoTiff::from_u16(n) {
+            return Self::GeoTiffTag(value);
+        }
+        if let Ok(value) = Gdal::from_u16(n) {
+            return Self::GdalTag(value);
+        }
         Self::PrivateTag(n)
     }
+    pub fn to_u16(&self) -> u16 {
+        match self {
+            Self::PrivateTag(id) => *id,
+            Self::BaselineTag(id) => id.to_u16(),
+            Self::ExtensionTag(id) => id.to_u16(),
+            Self::ExifTag(id) => id.to_u16(),
+            Self::GpsTag(id) => id.to_u16(),
+            Self::InteropTag(id) => id.to_u16(),
+            Self::GeoTiffTag(id) => id.to_u16(),
+            Self::GdalTag(id) => id.to_u16(),
+        }
+    }
+    // Reinterprets a tag read from a GPS IFD, where the generic lookup cannot tell them apart.
+    pub fn to_gps(&self) -> Self {
+        match Gps::from_u16(self.to_u16()) {
+            Ok(id) => Self::GpsTag(id),
+            Err(_) => self.clone(),
+        }
+    }
+    pub fn to_interop(&self) -> Self {
+        match Interop::from_u16(self.to_u16()) {
+            Ok(id) => Self::InteropTag(id),
+            Err(_) => self.clone(),
+        }
+    }
+    // For IFDs with a vendor defined numbering, e.g. MakerNotes.
+    pub fn to_private(&self) -> Self {
+        Self::PrivateTag(self.to_u16())
+    }
+}
+// Location of an out-of-line tag value that has not been read yet.
+#[derive(Debug, Clone)]
+pub struct Deferred {
+    pub kind: u16,
+    pub count: u64,
+    pub offset: u64,
+}
+impl Deferred {
+    pub fn byte_count(&self) -> Result<usize, TiffError> {
+        (self.count as usize).checked_mul(TagData::size_of_kind(self.kind)?).ok_or(TiffError::UnexpectedEndOfBuffer)
+    }
 }
 #[derive(Default)]
 pub struct Tag {
     id: TagID,
-    data: TagData,
+    data: OnceLock<TagData>,
+    deferred: Option<Deferred>,
+    offset: Option<u64>,
 }
 impl std::fmt::Debug for Tag {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
-        f.debug_struct("Tag")
-         .field("id", &self.id)
-         .field("data", &self.data)
-         .finish()
+        let mut s = f.debug_struct("Tag");
+        s.field("id", &self.id);
+        match (self.data.get(), &self.deferred) {
+            (None, Some(deferred)) => s.field("deferred", deferred),
+            _ => s.field("data", &self.data.get()),
+        };
+        s.finish()
     }
 }
 impl Tag {
-    pub fn from_buffer(buffer: &mut BufReader<File>, btf: bool, le: bool) -> Result<Self, TiffError> {
+    pub fn new(id: TagID, data: TagData) -> Self {
+        Self{id, data: OnceLock::from(data), deferred: None, offset: None}
+    }
+    pub(crate) fn with_offset(mut self, offset: Option<u64>) -> Self {
+        self.offset = offset;
+        self
+    }
+    pub fn from_buffer<R: Read + Seek>(buffer: &mut R, btf: bool, le: bool) -> Result<Self, TiffError> {
+        Self::from_buffer_deferred(buffer, btf, le, usize::MAX)
+    }
+    // Out-of-line values larger than `threshold` bytes are not read, for the tags of
+    // `is_deferrable`; only their (type, count, offset) is kept until `load` is called.
+    pub fn from_buffer_deferred<R: Read + Seek>(buffer: &mut R, btf: bool, le: bool, threshold: usize) -> Result<Self, TiffError> {
+        let tag = Self::from_entry(buffer, btf, le)?;
+        match &tag.deferred {
+            Some(deferred) if !tag.defers(threshold)? => {
+                let end = buffer.stream_position().or(Err(TiffError::UnknownBufferError))?;
+                let mut bytes = vec![0u8; deferred.byte_count()?];
+                buffer.seek(SeekFrom::Start(deferred.offset)).or(Err(TiffError::UnexpectedEndOfBuffer))?;
+                buffer.read_exact(&mut bytes).or(Err(TiffError::UnexpectedEndOfBuffer))?;
+                buffer.seek(SeekFrom::Start(end)).or(Err(TiffError::UnknownBufferError))?;
+                tag.resolve(&bytes, le)
+            },
+            _ => Ok(tag),
+        }
+    }
+    // Parses one IFD entry without reading anything outside of it: inline values are
+    // decoded, out-of-line values are left deferred.
+    pub(crate) fn from_entry<R: Read + Seek>(buffer: &mut R, btf: bool, le: bool) -> Result<Self, TiffError> {
         let id = TagID::from_u16(buffer_as!(buffer, u16, le)?);
+        let pos = buffer.stream_position().or(Err(TiffError::UnknownBufferError))?;
+        let kind = buffer_as!(buffer, u16, le)?;
+        let count = buffer_as_offset!(buffer, btf, le);
+        let byte_count = (count as usize).saturating_mul(TagData::size_of_kind(kind)?);
+        let offset_byte_count: usize = if btf { 8 } else { 4 };
+        if byte_count > offset_byte_count {
+            let offset = buffer_as_offset!(buffer, btf, le);
+            return Ok(Self{id, data: OnceLock::new(), deferred: Some(Deferred{kind, count, offset}), offset: Some(offset)});
+        }
+        buffer.seek(SeekFrom::Start(pos)).or(Err(TiffError::UnknownBufferError))?;
         let data = TagData::from_buffer(buffer, btf, le)?;
-        Ok(Self{id, data})
+        Ok(Self::new(id, data))
+    }
+    // Whether an out-of-line value of this size stays unread at parse time.
+    pub(crate) fn defers(&self, threshold: usize) -> Result<bool, TiffError> {
+        match &self.deferred {
+            Some(deferred) => Ok(deferred.byte_count()? > threshold && is_deferrable(&self.id)),
+            None => Ok(false),
+        }
+    }
+    // Decodes the bytes of a deferred value read at parse time, so the tag is no
+    // longer reported as deferred.
+    pub(crate) fn resolve(mut self, bytes: &[u8], le: bool) -> Result<Self, TiffError> {
+        self.load_bytes(bytes, le)?;
+        self.deferred = None;
+        Ok(self)
+    }
+    pub fn id(&self) -> &TagID {
+        &self.id
+    }
+    // File offset of the value, when it is stored out of line.
+    pub fn offset(&self) -> Option<u64> {
+        self.offset
+    }
+    pub fn deferred(&self) -> Option<&Deferred> {
+        self.deferred.as_ref()
+    }
+    pub fn is_loaded(&self) -> bool {
+        self.data.get().is_some()
+    }
+    pub fn data(&self) -> Result<&TagData, TiffError> {
+        self.data.get().ok_or(TiffError::TagDataNotLoaded)
+    }
+    // Reads a deferred value from `buffer`; values already in memory are returned as is.
+    pub fn load<R: Read + Seek>(&self, buffer: &mut R, le: bool) -> Result<&TagData, TiffError> {
+        if let Some(data) = self.data.get() {
+            return Ok(data);
+        }
+        let deferred = self.deferred.as_ref().ok_or(TiffError::TagDataNotLoaded)?;
+        let mut bytes = vec![0u8; deferred.byte_count()?];
+        buffer.seek(SeekFrom::Start(deferred.offset)).or(Err(TiffError::UnexpectedEndOfBuffer))?;
+        buffer.read_exact(&mut bytes).or(Err(TiffError::UnexpectedEndOfBuffer))?;
+        self.load_bytes(&bytes, le)
+    }
+    // Sets a deferred value from its bytes, e.g. as fetched by an async reader.
+    pub(crate) fn load_bytes(&self, bytes: &[u8], le: bool) -> Result<&TagData, TiffError> {
+        if let Some(data) = self.data.get() {
+            return Ok(data);
+        }
+        let deferred = self.deferred.as_ref().ok_or(TiffError::TagDataNotLoaded)?;
+        let data = TagData::from_bytes(deferred.kind, deferred.count as usize, bytes, le)?;
+        Ok(self.data.get_or_init(|| data))
     }
 }
 
+// Deepest SubIFD level followed, so a malformed file cannot recurse forever.
+pub(crate) const MAX_SUB_IFD_DEPTH: usize = 8;
+
+// Where an IFD reached through a pointer tag hangs under the IFD holding the tag.
+// Shared by `IFD` and `IFDBytes` so both trees are built the same way.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum PointerSlot {
+    SubIFD,
+    Exif,
+    Gps,
+    Interop,
+}
+impl PointerSlot {
+    pub(crate) fn from_id(id: u16) -> Option<Self> {
+        if id == Extension::SubIFDs.to_u16() {
+            Some(Self::SubIFD)
+        } else if id == Exif::ExifIFD.to_u16() {
+            Some(Self::Exif)
+        } else if id == Exif::GPSInfo.to_u16() {
+            Some(Self::Gps)
+        } else if id == Exif::InteroperabilityIFD.to_u16() {
+            Some(Self::Interop)
+        } else {
+            None
+        }
+    }
+    // GPS and Interop IFDs have their own numbering; their tags are parsed as private tags.
+    pub(crate) fn retag(&self, id: &TagID) -> TagID {
+        match self {
+            Self::Gps => id.to_gps(),
+            Self::Interop => id.to_interop(),
+            Self::SubIFD | Self::Exif => id.clone(),
+        }
+    }
+    // Defer threshold for the IFD behind this pointer. GPS and Interop tags still look
+    // like private tags when deferral is decided, so nothing there is deferred.
+    pub(crate) fn threshold(&self, threshold: usize) -> usize {
+        match self {
+            Self::Gps | Self::Interop => usize::MAX,
+            Self::SubIFD | Self::Exif => threshold,
+        }
+    }
+}
+
+// Tags whose values are offsets of further IFDs, parsed along with the IFD holding them.
+pub(crate) fn is_ifd_pointer(id: u16) -> bool {
+    PointerSlot::from_id(id).is_some()
+}
+
+// Tags that may be left deferred: bulky values read through `Tiff::load_tag` or not
+// read by this crate. Everything the typed accessors and decoding need is always loaded.
+pub(crate) fn is_deferrable(id: &TagID) -> bool {
+    matches!(id,
+        TagID::BaselineTag(Baseline::StripOffsets | Baseline::StripByteCounts | Baseline::ColorMap)
+        | TagID::ExtensionTag(Extension::TileOffsets | Extension::TileByteCounts | Extension::JPEGTables | Extension::XMP)
+        | TagID::ExifTag(Exif::MakerNote)
+        | TagID::PrivateTag(_))
+}
+
 #[derive(Debug, Default)]
 pub struct IFD {
     pub pos: u64,
     pub tag_count: u64,
     pub tags: Vec<Tag>,
     pub next_ifd: u64,
+    pub sub_ifd: Vec<IFD>,
+    pub exif_ifd: Option<Box<IFD>>,
+    pub gps_ifd: Option<Box<IFD>>,
+    pub interop_ifd: Option<Box<IFD>>,
 }
 impl IFD {
-    pub fn from_buffer(buffer: &mut BufReader<File>, btf: bool, le: bool, skip: bool) -> Result<Self, TiffError> {
+    pub fn from_buffer<R: Read + Seek>(buffer: &mut R, btf: bool, le: bool, skip: bool) -> Result<Self, TiffError> {
+        Self::from_buffer_deferred(buffer, btf, le, skip, usize::MAX)
+    }
+    pub fn from_buffer_deferred<R: Read + Seek>(buffer: &mut R, btf: bool, le: bool, skip: bool, threshold: usize) -> Result<Self, TiffError> {
+        let mut visited: HashSet<u64> = HashSet::new();
+        Self::from_buffer_depth(buffer, btf, le, skip, threshold, 0, &mut visited)
+    }
+    // `visited` holds the offsets of all the IFDs of the tree parsed so far, so an IFD
+    // reached again through another pointer is not parsed (and recursed into) twice.
+    fn from_buffer_depth<R: Read + Seek>(buffer: &mut R, btf: bool, le: bool, skip: bool, threshold: usize, depth: usize, visited: &mut HashSet<u64>) -> Result<Self, TiffError> {
         let pos = buffer.stream_position().or(Err(TiffError::UnknownBufferError))?;
+        visited.insert(pos);
         let tag_count = if btf {
             buffer_as!(buffer, u64, le)?
         } else {
             buffer_as!(buffer, u16, le)? as u64
         };
         let mut tags: Vec<Tag> = vec![];
+        let mut pointers: Vec<(u16, Vec<u64>)> = vec![];
         if skip {
-            let tag_byte_count = if btf {
-                tag_count * 20
-            } else {
-                tag_count * 12
-            };
-            buffer.seek(SeekFrom::Current(tag_byte_count as i64)).or(Err(TiffError::UnexpectedEndOfBuffer))?;
+            // only IFD pointer entries are decoded, so the IFD tree is still complete
+            // entries are read one at a time, as the count comes from the file
+            let entry_size: u64 = if btf { 20 } else { 12 };
+            let mut rest = [0u8; 18];
+            for _ in 0..tag_count {
+                let entry_pos = buffer.stream_position().or(Err(TiffError::UnknownBufferError))?;
+                let id = buffer_as!(buffer, u16, le)?;
+                if is_ifd_pointer(id) {
+                    pointers.push((id, TagData::from_buffer(buffer, btf, le)?.as_unsigned_integers()?));
+                    buffer.seek(SeekFrom::Start(entry_pos + entry_size)).or(Err(TiffError::UnexpectedEndOfBuffer))?;
+                } else {
+                    buffer.read_exact(&mut rest[..entry_size as usize - 2]).or(Err(TiffError::UnexpectedEndOfBuffer))?;
+                }
+            }
         } else {
             for _ in 0..tag_count {
-                let tag = Tag::from_buffer(buffer, btf, le)?;
+                let tag = Tag::from_buffer_deferred(buffer, btf, le, threshold)?;
+                let id = tag.id.to_u16();
+                if is_ifd_pointer(id) {
+                    let end = buffer.stream_position().or(Err(TiffError::UnknownBufferError))?;
+                    pointers.push((id, tag.load(buffer, le)?.as_unsigned_integers()?));
+                    buffer.seek(SeekFrom::Start(end)).or(Err(TiffError::UnexpectedEndOfBuffer))?;
+                }
                 tags.push(tag);
             }
         }
         let next_ifd = buffer_as_offset!(buffer, btf, le);
 
-        Ok(Self{pos, tag_count, tags, next_ifd})
+        let mut directory = Self{pos, tag_count, tags, next_ifd, ..Default::default()};
+        if depth < MAX_SUB_IFD_DEPTH && !pointers.is_empty() {
+            let end = buffer.stream_position().or(Err(TiffError::UnknownBufferError))?;
+            for (id, offsets) in pointers {
+                let threshold = PointerSlot::from_id(id).map_or(threshold, |slot| slot.threshold(threshold));
+                for offset in offsets {
+                    if !visited.insert(offset) {
+                        continue;
+                    }
+                    // a broken pointer leaves the child unset instead of failing the whole file
+                    let child = buffer.seek(SeekFrom::Start(offset)).or(Err(TiffError::UnexpectedEndOfBuffer))
+                        .and_then(|_| Self::from_buffer_depth(buffer, btf, le, skip, threshold, depth + 1, visited));
+                    if let Ok(child) = child {
+                        directory.attach(id, child);
+                    }
+                }
+            }
+            buffer.seek(SeekFrom::Start(end)).or(Err(TiffError::UnexpectedEndOfBuffer))?;
+        }
+
+        Ok(directory)
+    }
+    pub(crate) fn retag(&mut self, f: impl Fn(&TagID) -> TagID) {
+        for tag in self.tags.iter_mut() {
+            tag.id = f(&tag.id);
+        }
+    }
+    // Hangs an IFD reached through pointer tag `id` under this one.
+    pub(crate) fn attach(&mut self, id: u16, mut child: IFD) {
+        let slot = match PointerSlot::from_id(id) {
+            Some(slot) => slot,
+            None => return,
+        };
+        child.retag(|tag| slot.retag(tag));
+        match slot {
+            PointerSlot::SubIFD => self.sub_ifd.push(child),
+            PointerSlot::Exif => self.exif_ifd = Some(Box::new(child)),
+            PointerSlot::Gps => self.gps_ifd = Some(Box::new(child)),
+            PointerSlot::Interop => self.interop_ifd = Some(Box::new(child)),
+        }
+    }
+    // This IFD followed by all of its SubIFDs, depth first (private IFDs such as EXIF are not included).
+    pub fn descendants(&self) -> Vec<&IFD> {
+        let mut list = vec![self];
+        for child in &self.sub_ifd {
+            list.extend(child.descendants());
+        }
+        list
     }
     pub fn get_tag(&self, id: TagID) -> Result<&Tag, TiffError> {
         for tag in &self.tags {
@@ -467,37 +909,327 @@ impl IFD {
                 return Ok(tag);
             }
         }
-        return Err(TiffError::CannotFindTag);
+        Err(TiffError::CannotFindTag)
     }
     pub fn width(&self) -> Result<u64, TiffError> {
         let tag = self.get_tag(TagID::BaselineTag(Baseline::ImageWidth))?;
-        tag.data.as_unsigned_integer()
+        tag.data()?.as_unsigned_integer()
     }
     pub fn height(&self) -> Result<u64, TiffError> {
         let tag = self.get_tag(TagID::BaselineTag(Baseline::ImageLength))?;
-        tag.data.as_unsigned_integer()
+        tag.data()?.as_unsigned_integer()
     }
     pub fn samples(&self) -> Result<u64, TiffError> {
         // default: 1
         if let Ok(tag) = self.get_tag(TagID::BaselineTag(Baseline::SamplesPerPixel)) {
-            return tag.data.as_unsigned_integer();
+            return tag.data()?.as_unsigned_integer();
         }
         Ok(1_u64)
     }
     pub fn bpp(&self) -> Result<Vec<u64>, TiffError> {
         if let Ok(tag) = self.get_tag(TagID::BaselineTag(Baseline::BitsPerSample)) {
-            return tag.data.as_unsigned_integers();
+            return tag.data()?.as_unsigned_integers();
         }
-        return Ok(vec![1_u64; self.samples()? as usize]);
+        Ok(vec![1_u64; self.samples()? as usize])
     }
     pub fn compression(&self) -> Result<CompressionScheme, TiffError> {
         // default: 1 (no compression)
         if let Ok(tag) = self.get_tag(TagID::BaselineTag(Baseline::Compression)) {
-            match CompressionScheme::from_number(tag.data.as_unsigned_integer()?) {
+            match CompressionScheme::from_number(tag.data()?.as_unsigned_integer()?) {
                 Some(value) => return Ok(value),
                 None => return Err(TiffError::NotSupportedCompressionScheme),
             }
         }
         Ok(CompressionScheme::NoCompression)
     }
+    pub fn orientation(&self) -> Result<Orientation, TiffError> {
+        // default: 1 (top-left)
+        if let Ok(tag) = self.get_tag(TagID::BaselineTag(Baseline::Orientation)) {
+            match Orientation::from_number(tag.data()?.as_unsigned_integer()?) {
+                Some(value) => return Ok(value),
+                None => return Err(TiffError::NotSupportedOrientation),
+            }
+        }
+        Ok(Orientation::TopLeft)
+    }
+    pub fn fill_order(&self) -> Result<FillOrder, TiffError> {
+        // default: 1 (most significant bit first)
+        if let Ok(tag) = self.get_tag(TagID::BaselineTag(Baseline::FillOrder)) {
+            match FillOrder::from_number(tag.data()?.as_unsigned_integer()?) {
+                Some(value) => return Ok(value),
+                None => return Err(TiffError::NotSupportedFillOrder),
+            }
+        }
+        Ok(FillOrder::MsbToLsb)
+    }
+    pub fn photometric(&self) -> Result<Photometric, TiffError> {
+        let tag = self.get_tag(TagID::BaselineTag(Baseline::PhotometricInterpretation))?;
+        match Photometric::from_number(tag.data()?.as_unsigned_integer()?) {
+            Some(value) => Ok(value),
+            None => Err(TiffError::NotSupportedPhotometric),
+        }
+    }
+    pub fn threshholding(&self) -> Result<u64, TiffError> {
+        // default: 1 (no dithering or halftoning)
+        if let Ok(tag) = self.get_tag(TagID::BaselineTag(Baseline::Threshholding)) {
+            return tag.data()?.as_unsigned_integer();
+        }
+        Ok(1_u64)
+    }
+    pub fn decode(&self) -> Result<Vec<f64>, TiffError> {
+        // [min, max] pair per sample
+        let tag = self.get_tag(TagID::ExtensionTag(Extension::Decode))?;
+        let values = tag.data()?.as_floating_points()?;
+        if values.len() != 2 * self.samples()? as usize {
+            return Err(TiffError::IncompatibleTagDataKind);
+        }
+        Ok(values)
+    }
+    pub fn default_image_color(&self) -> Result<Vec<u64>, TiffError> {
+        let tag = self.get_tag(TagID::ExtensionTag(Extension::DefaultImageColor))?;
+        tag.data()?.as_unsigned_integers()
+    }
+    pub fn gray_response_unit(&self) -> Result<u64, TiffError> {
+        // default: 2 (hundredths of a unit)
+        if let Ok(tag) = self.get_tag(TagID::BaselineTag(Baseline::GrayResponseUnit)) {
+            return tag.data()?.as_unsigned_integer();
+        }
+        Ok(2_u64)
+    }
+    pub fn gray_response_curve(&self) -> Result<Vec<f64>, TiffError> {
+        // optical densities, already scaled by GrayResponseUnit
+        let tag = self.get_tag(TagID::BaselineTag(Baseline::GrayResponseCurve))?;
+        let scale = 10f64.powi(self.gray_response_unit()? as i32);
+        Ok(tag.data()?.as_unsigned_integers()?.iter().map(|v| *v as f64 / scale).collect())
+    }
+    pub fn transfer_function(&self) -> Result<Vec<Vec<u64>>, TiffError> {
+        // one table shared by all samples, or one table per color sample
+        let tag = self.get_tag(TagID::ExtensionTag(Extension::TransferFunction))?;
+        let values = tag.data()?.as_unsigned_integers()?;
+        let bits = self.bpp()?.first().copied().unwrap_or(1);
+        // 2^BitsPerSample entries per table
+        if bits > 16 {
+            return Err(TiffError::IncompatibleTagDataKind);
+        }
+        let size = 1_usize << bits;
+        if values.len() != size && values.len() != 3 * size {
+            return Err(TiffError::IncompatibleTagDataKind);
+        }
+        Ok(values.chunks(size).map(|table| table.to_vec()).collect())
+    }
+    pub fn transfer_range(&self) -> Result<Vec<u64>, TiffError> {
+        // [black, white] pair per color sample
+        let tag = self.get_tag(TagID::ExtensionTag(Extension::TransferRange))?;
+        tag.data()?.as_unsigned_integers()
+    }
+    pub fn new_subfile_type(&self) -> Result<u64, TiffError> {
+        // default: 0 (full resolution image)
+        if let Ok(tag) = self.get_tag(TagID::BaselineTag(Baseline::NewSubfileType)) {
+            return tag.data()?.as_unsigned_integer();
+        }
+        Ok(0_u64)
+    }
+    // NewSubfileType bit 0
+    pub fn is_reduced_resolution(&self) -> bool {
+        self.new_subfile_type().is_ok_and(|value| value & 1 != 0)
+    }
+    // NewSubfileType bit 2, a transparency mask for another image
+    pub fn is_mask(&self) -> bool {
+        self.new_subfile_type().is_ok_and(|value| value & 4 != 0)
+    }
+    // mask flag plus PhotometricInterpretation 4, as written by GDAL
+    pub fn is_transparency_mask(&self) -> bool {
+        self.is_mask() && self.photometric().is_ok_and(|value| value == Photometric::TransparencyMask)
+    }
+    pub fn is_tiled(&self) -> bool {
+        self.get_tag(TagID::ExtensionTag(Extension::TileWidth)).is_ok()
+    }
+    pub fn tile_width(&self) -> Result<u64, TiffError> {
+        let tag = self.get_tag(TagID::ExtensionTag(Extension::TileWidth))?;
+        tag.data()?.as_unsigned_integer()
+    }
+    pub fn tile_length(&self) -> Result<u64, TiffError> {
+        let tag = self.get_tag(TagID::ExtensionTag(Extension::TileLength))?;
+        tag.data()?.as_unsigned_integer()
+    }
+    pub fn rows_per_strip(&self) -> Result<u64, TiffError> {
+        // default: 2**32 - 1 (a single strip)
+        if let Ok(tag) = self.get_tag(TagID::BaselineTag(Baseline::RowsPerStrip)) {
+            return Ok(tag.data()?.as_unsigned_integer()?.min(self.height()?));
+        }
+        self.height()
+    }
+    pub fn planar_configuration(&self) -> Result<u64, TiffError> {
+        // default: 1 (chunky)
+        if let Ok(tag) = self.get_tag(TagID::BaselineTag(Baseline::PlanarConfiguration)) {
+            return tag.data()?.as_unsigned_integer();
+        }
+        Ok(1_u64)
+    }
+    pub fn pixel_size(&self) -> Result<usize, TiffError> {
+        // bytes per pixel of chunky, byte-aligned data
+        let bits: u64 = self.bpp()?.iter().sum();
+        if self.planar_configuration()? != 1 || !bits.is_multiple_of(8) {
+            return Err(TiffError::NotSupportedSampleLayout);
+        }
+        Ok((bits / 8) as usize)
+    }
+    // (width, height) of a strip or tile, and the number of chunks per row
+    pub fn chunk_layout(&self) -> Result<(u64, u64, u64), TiffError> {
+        if self.is_tiled() {
+            let (tile_width, tile_length) = (self.tile_width()?, self.tile_length()?);
+            if tile_width == 0 || tile_length == 0 {
+                return Err(TiffError::IncompatibleTagDataKind);
+            }
+            Ok((tile_width, tile_length, self.width()?.div_ceil(tile_width)))
+        } else {
+            Ok((self.width()?, self.rows_per_strip()?.max(1), 1))
+        }
+    }
+    pub fn predictor(&self) -> Result<Predictor, TiffError> {
+        // default: 1 (no prediction)
+        if let Ok(tag) = self.get_tag(TagID::ExtensionTag(Extension::Predictor)) {
+            match Predictor::from_number(tag.data()?.as_unsigned_integer()?) {
+                Some(value) => return Ok(value),
+                None => return Err(TiffError::NotSupportedPredictor),
+            }
+        }
+        Ok(Predictor::NoPrediction)
+    }
+    pub fn sample_format(&self) -> Result<u64, TiffError> {
+        // default: 1 (unsigned integer); all samples are assumed to share the first format
+        if let Ok(tag) = self.get_tag(TagID::ExtensionTag(Extension::SampleFormat)) {
+            if let Some(value) = tag.data()?.as_unsigned_integers()?.first() {
+                return Ok(*value);
+            }
+        }
+        Ok(1_u64)
+    }
+    pub fn gdal_metadata(&self) -> Result<GdalMetadata, TiffError> {
+        GdalMetadata::from_xml(&self.get_tag(TagID::GdalTag(Gdal::GDALMetadata))?.data()?.as_string()?)
+    }
+    pub fn gdal_nodata(&self) -> Result<NoData, TiffError> {
+        NoData::parse(&self.get_tag(TagID::GdalTag(Gdal::GDALNoData))?.data()?.as_string()?, self.sample_format()?)
+    }
+    pub fn compression_number(&self) -> Result<u64, TiffError> {
+        // default: 1 (no compression)
+        if let Ok(tag) = self.get_tag(TagID::BaselineTag(Baseline::Compression)) {
+            return tag.data()?.as_unsigned_integer();
+        }
+        Ok(1_u64)
+    }
+    pub fn exif(&self) -> Result<&IFD, TiffError> {
+        self.exif_ifd.as_deref().ok_or(TiffError::CannotFindTag)
+    }
+    // EXIF values are looked up in the EXIF IFD when this IFD points to one.
+    fn exif_tag(&self, id: Exif) -> Result<&TagData, TiffError> {
+        self.exif().unwrap_or(self).get_tag(TagID::ExifTag(id))?.data()
+    }
+    // seconds
+    pub fn exposure_time(&self) -> Result<f64, TiffError> {
+        self.exif_tag(Exif::ExposureTime)?.as_floating_point()
+    }
+    pub fn f_number(&self) -> Result<f64, TiffError> {
+        self.exif_tag(Exif::FNumber)?.as_floating_point()
+    }
+    pub fn iso(&self) -> Result<u64, TiffError> {
+        match self.exif_tag(Exif::ISOSpeedRatings)?.as_unsigned_integers()?.first() {
+            Some(value) => Ok(*value),
+            None => Err(TiffError::IncompatibleTagDataKind),
+        }
+    }
+    // "YYYY:MM:DD HH:MM:SS"
+    pub fn date_time_original(&self) -> Result<String, TiffError> {
+        self.exif_tag(Exif::DateTimeOriginal)?.as_string()
+    }
+    pub fn date_time_digitized(&self) -> Result<String, TiffError> {
+        self.exif_tag(Exif::DateTimeDigitized)?.as_string()
+    }
+    // millimeters
+    pub fn focal_length(&self) -> Result<f64, TiffError> {
+        self.exif_tag(Exif::FocalLength)?.as_floating_point()
+    }
+    pub fn focal_length_35mm(&self) -> Result<u64, TiffError> {
+        self.exif_tag(Exif::FocalLengthIn35mmFilm)?.as_unsigned_integer()
+    }
+    pub fn lens_make(&self) -> Result<String, TiffError> {
+        self.exif_tag(Exif::LensMake)?.as_string()
+    }
+    pub fn lens_model(&self) -> Result<String, TiffError> {
+        self.exif_tag(Exif::LensModel)?.as_string()
+    }
+    pub fn body_serial_number(&self) -> Result<String, TiffError> {
+        self.exif_tag(Exif::BodySerialNumber)?.as_string()
+    }
+    // The Interoperability IFD hangs off the EXIF IFD.
+    pub fn interop(&self) -> Result<&IFD, TiffError> {
+        let exif = self.exif().unwrap_or(self);
+        exif.interop_ifd.as_deref().ok_or(TiffError::CannotFindTag)
+    }
+    // e.g. "R98" for DCF basic files
+    pub fn interoperability_index(&self) -> Result<String, TiffError> {
+        self.interop()?.get_tag(TagID::InteropTag(Interop::InteroperabilityIndex))?.data()?.as_string()
+    }
+    pub fn maker_note_tag(&self) -> Result<&Tag, TiffError> {
+        self.exif().unwrap_or(self).get_tag(TagID::ExifTag(Exif::MakerNote))
+    }
+    pub fn make(&self) -> Result<String, TiffError> {
+        self.get_tag(TagID::BaselineTag(Baseline::Make))?.data()?.as_string()
+    }
+    // (ScaleX, ScaleY, ScaleZ)
+    pub fn model_pixel_scale(&self) -> Result<Vec<f64>, TiffError> {
+        self.get_tag(TagID::GeoTiffTag(GeoTiff::ModelPixelScale))?.data()?.as_floating_points()
+    }
+    // (I, J, K, X, Y, Z) raster to model tiepoints, six values each
+    pub fn model_tiepoints(&self) -> Result<Vec<f64>, TiffError> {
+        self.get_tag(TagID::GeoTiffTag(GeoTiff::ModelTiepoint))?.data()?.as_floating_points()
+    }
+    // 4x4 row-major raster to model matrix
+    pub fn model_transformation(&self) -> Result<Vec<f64>, TiffError> {
+        self.get_tag(TagID::GeoTiffTag(GeoTiff::ModelTransformation))?.data()?.as_floating_points()
+    }
+    pub fn geo_keys(&self) -> Result<GeoKeyDirectory, TiffError> {
+        GeoKeyDirectory::from_ifd(self)
+    }
+    pub fn geo_transform(&self) -> Result<GeoTransform, TiffError> {
+        GeoTransform::from_ifd(self)
+    }
+    // (min_x, min_y, max_x, max_y) in model coordinates
+    pub fn bounding_box(&self) -> Result<(f64, f64, f64, f64), TiffError> {
+        Ok(self.geo_transform()?.bounds(self.width()?, self.height()?))
+    }
+    pub fn gps(&self) -> Result<&IFD, TiffError> {
+        self.gps_ifd.as_deref().ok_or(TiffError::CannotFindTag)
+    }
+    fn gps_tag(&self, id: Gps) -> Result<&TagData, TiffError> {
+        self.gps().unwrap_or(self).get_tag(TagID::GpsTag(id))?.data()
+    }
+    // degrees/minutes/seconds rationals as decimal degrees, negated for the `negative` reference
+    fn gps_coordinate(&self, value: Gps, reference: Gps, negative: &str) -> Result<f64, TiffError> {
+        let dms = self.gps_tag(value)?.as_floating_points()?;
+        let mut degrees = 0.0;
+        for (value, scale) in dms.iter().zip([1.0, 60.0, 3600.0]) {
+            degrees += value / scale;
+        }
+        match self.gps_tag(reference) {
+            Ok(data) if data.as_string()?.eq_ignore_ascii_case(negative) => Ok(-degrees),
+            _ => Ok(degrees),
+        }
+    }
+    // decimal degrees, south negative
+    pub fn latitude(&self) -> Result<f64, TiffError> {
+        self.gps_coordinate(Gps::Latitude, Gps::LatitudeRef, "S")
+    }
+    // decimal degrees, west negative
+    pub fn longitude(&self) -> Result<f64, TiffError> {
+        self.gps_coordinate(Gps::Longitude, Gps::LongitudeRef, "W")
+    }
+    // meters, below sea level negative
+    pub fn altitude(&self) -> Result<f64, TiffError> {
+        let altitude = self.gps_tag(Gps::Altitude)?.as_floating_point()?;
+        match self.gps_tag(Gps::AltitudeRef) {
+            Ok(data) if data.as_unsigned_integers()?.first() == Some(&1) => Ok(-altitude),
+            _ => Ok(altitude),
+        }
+    }
 }
@@ -1,4 +1,5 @@
 #![allow(unused_imports)]
+#![allow(clippy::upper_case_acronyms)]
 
 mod error;
 use error::TiffError;
@@ -7,7 +8,7 @@ use error::TiffError;
 mod utils;
 
 mod enums;
-use enums::{CompressionScheme};
+use enums::{CompressionScheme, Orientation, FillOrder, Photometric, Predictor, ModelType, RasterType, GeoKeyId};
 
 mod header;
 use header::Header;
@@ -18,6 +19,40 @@ use ifd::IFD;
 mod tiff;
 use tiff::Tiff;
 
+mod bytes;
+use bytes::TiffBytes;
+
+mod range;
+use range::{RangeFetcher, RangeReader};
+
+#[cfg(feature = "async")]
+mod asynctiff;
+#[cfg(feature = "async")]
+use asynctiff::AsyncTiff;
+
+mod cache;
+use cache::ChunkCache;
+
+mod decode;
+
+mod geotiff;
+use geotiff::{GeoKeyDirectory, GeoKey, GeoKeyValue, GeoTransform};
+
+mod gdal;
+use gdal::{GdalMetadata, GdalMetadataItem, NoData};
+
+mod cog;
+use cog::{validate_cog, CogReport, CogFinding, CogRule, CogSeverity};
+
+mod pyramid;
+use pyramid::{Pyramid, PyramidLevel, associate_masks};
+
+mod makernote;
+use makernote::{MakerNote, MakerNoteVendor};
+
+mod transform;
+use transform::{apply_orientation, apply_fill_order, invert_samples, apply_decode, fill_default_color, unpack_samples, apply_transfer_function, apply_gray_response};
+
 
 
 #[cfg(test)]
@@ -47,4 +82,854 @@ mod tests {
             println!("Compression: {:?}", compression);
         }
     }
+    #[test]
+    fn orientation() {
+        use super::Orientation;
+        // 3x2 image, one byte per pixel
+        let data = [1u8, 2, 3, 4, 5, 6];
+        let (out, w, h) = super::apply_orientation(&data, 3, 2, 1, &Orientation::TopRight).unwrap();
+        assert_eq!((out, w, h), (vec![3, 2, 1, 6, 5, 4], 3, 2));
+        let (out, w, h) = super::apply_orientation(&data, 3, 2, 1, &Orientation::RightTop).unwrap();
+        assert_eq!((out, w, h), (vec![4, 1, 5, 2, 6, 3], 2, 3));
+        let (out, w, h) = super::apply_orientation(&data, 3, 2, 1, &Orientation::LeftBottom).unwrap();
+        assert_eq!((out, w, h), (vec![3, 6, 2, 5, 1, 4], 2, 3));
+    }
+    #[test]
+    fn oriented_read() {
+        // 3x2 image stored bottom-right first
+        let data = tiff_with_strip(&[1, 2, 3, 4, 5, 6], &[
+            (0x0100, 3, 1, shorts(&[3])),
+            (0x0101, 3, 1, shorts(&[2])),
+            (0x0102, 3, 1, shorts(&[8])),
+            (0x0111, 4, 1, 8u32.to_le_bytes().to_vec()),
+            (0x0112, 3, 1, shorts(&[3])),
+            (0x0117, 4, 1, 6u32.to_le_bytes().to_vec()),
+        ]);
+        let tiff = super::Tiff::from_reader(std::io::Cursor::new(data), false).unwrap();
+        let ifd = &tiff.ifd[0];
+        assert_eq!(ifd.orientation().unwrap(), super::Orientation::BottomRight);
+        assert_eq!(tiff.read_image(ifd).unwrap(), vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(tiff.read_image_oriented(ifd).unwrap(), (vec![6, 5, 4, 3, 2, 1], 3, 2));
+    }
+    #[test]
+    fn fill_order() {
+        // 8x2 bilevel image, one byte per row stored least significant bit first
+        let data = tiff_with_strip(&[0b0000_0001, 0b1100_1010], &[
+            (0x0100, 3, 1, shorts(&[8])),
+            (0x0101, 3, 1, shorts(&[2])),
+            (0x0102, 3, 1, shorts(&[1])),
+            (0x010A, 3, 1, shorts(&[2])),
+            (0x0111, 4, 1, 8u32.to_le_bytes().to_vec()),
+            (0x0117, 4, 1, 2u32.to_le_bytes().to_vec()),
+        ]);
+        let tiff = super::Tiff::from_reader(std::io::Cursor::new(data), false).unwrap();
+        let ifd = &tiff.ifd[0];
+        assert_eq!(ifd.fill_order().unwrap(), super::FillOrder::LsbToMsb);
+        assert_eq!(tiff.read_raw_strip(ifd, 0).unwrap().data, vec![0b0000_0001, 0b1100_1010]);
+        assert_eq!(*tiff.read_chunk(ifd, 0).unwrap(), vec![0b1000_0000, 0b0101_0011]);
+    }
+    #[test]
+    fn white_is_zero() {
+        let mut samples = [0u8, 10, 255];
+        super::invert_samples(&mut samples);
+        assert_eq!(samples, [255, 245, 0]);
+        let mut pixels = [0u8; 6];
+        super::fill_default_color(&mut pixels, &[1, 2, 3]).unwrap();
+        assert_eq!(pixels, [1, 2, 3, 1, 2, 3]);
+        assert!(super::fill_default_color(&mut pixels, &[1, 2, 3, 4]).is_err());
+        // 2x2 WhiteIsZero image in two strips of one row, the second absent from the file
+        let data = tiff_with_strip(&[10, 20], &[
+            (0x0100, 3, 1, shorts(&[2])),
+            (0x0101, 3, 1, shorts(&[2])),
+            (0x0102, 3, 1, shorts(&[8])),
+            (0x0106, 3, 1, shorts(&[0])),
+            (0x0111, 4, 2, [8u32, 0].iter().flat_map(|v| v.to_le_bytes()).collect()),
+            (0x0116, 3, 1, shorts(&[1])),
+            (0x0117, 4, 2, [2u32, 0].iter().flat_map(|v| v.to_le_bytes()).collect()),
+            (0x01B1, 5, 2, [rational(1, 1), rational(0, 1)].concat()),
+            (0x01B2, 3, 1, shorts(&[200])),
+        ]);
+        let tiff = super::Tiff::from_reader(std::io::Cursor::new(data), false).unwrap();
+        let ifd = &tiff.ifd[0];
+        assert_eq!(ifd.photometric().unwrap(), super::Photometric::WhiteIsZero);
+        assert_eq!(ifd.decode().unwrap(), vec![1.0, 0.0]);
+        assert_eq!(ifd.default_image_color().unwrap(), vec![200]);
+        assert_eq!(tiff.read_raw_strip(ifd, 1).unwrap().data, vec![]);
+        assert_eq!(tiff.read_image(ifd).unwrap(), vec![245, 235, 55, 55]);
+    }
+    #[test]
+    fn codecs_and_predictor() {
+        use std::io::Write;
+        use super::decode::{ChunkDecoder, decode_chunks};
+        // 4x1 gray strip stored as horizontal differences
+        let differences = [10u8, 1, 1, 255];
+        let mut deflate = flate2::write::ZlibEncoder::new(vec![], flate2::Compression::default());
+        deflate.write_all(&differences).unwrap();
+        let deflate = deflate.finish().unwrap();
+        let lzw = weezl::encode::Encoder::with_tiff_size_switch(weezl::BitOrder::Msb, 8).encode(&differences).unwrap();
+        let packbits = vec![0x00, 10, 0xFF, 1, 0x00, 255];
+        for (compression, stored) in [(1, differences.to_vec()), (5, lzw), (8, deflate.clone()), (32946, deflate), (32773, packbits)] {
+            let data = tiff_with_strip(&stored, &[
+                (0x0100, 3, 1, shorts(&[4])),
+                (0x0101, 3, 1, shorts(&[1])),
+                (0x0102, 3, 1, shorts(&[8])),
+                (0x0103, 3, 1, shorts(&[compression])),
+                (0x0111, 4, 1, 8u32.to_le_bytes().to_vec()),
+                (0x0117, 4, 1, (stored.len() as u32).to_le_bytes().to_vec()),
+                (0x013D, 3, 1, shorts(&[2])),
+            ]);
+            let tiff = super::Tiff::from_reader(std::io::Cursor::new(data), false).unwrap();
+            assert_eq!(tiff.read_image(&tiff.ifd[0]).unwrap(), vec![10, 11, 12, 11], "compression {}", compression);
+        }
+        // 3x1 16 bit samples in big-endian order, with a carry into the high byte
+        let data = tiff_with_strip(&[0; 6], &[
+            (0x0100, 3, 1, shorts(&[3])),
+            (0x0101, 3, 1, shorts(&[1])),
+            (0x0102, 3, 1, shorts(&[16])),
+            (0x0111, 4, 1, 8u32.to_le_bytes().to_vec()),
+            (0x0117, 4, 1, 6u32.to_le_bytes().to_vec()),
+            (0x013D, 3, 1, shorts(&[2])),
+        ]);
+        let tiff = super::Tiff::from_reader(std::io::Cursor::new(data), false).unwrap();
+        let decoder = ChunkDecoder::from_ifd(&tiff.ifd[0], false).unwrap();
+        let stored = vec![0x01, 0xFF, 0x00, 0x02, 0x00, 0xFF];
+        let decoded = decode_chunks(&decoder, vec![stored.clone(); 4]).unwrap();
+        assert!(decoded.iter().all(|chunk| *chunk == [0x01, 0xFF, 0x02, 0x01, 0x03, 0x00]));
+    }
+    #[test]
+    fn linearization() {
+        let decoded = super::apply_decode(&[0, 255], 8, &[1.0, 0.0]).unwrap();
+        assert_eq!(decoded, vec![1.0, 0.0]);
+        let table: Vec<u64> = (0..4).map(|v| v * v * 65535 / 9).collect();
+        let linear = super::apply_transfer_function(&[0, 3, 1], &[table]).unwrap();
+        assert_eq!(linear[0], 0.0);
+        assert_eq!(linear[1], 1.0);
+        let density = super::apply_gray_response(&[1], &[0.0, 0.5]).unwrap();
+        assert_eq!(density, vec![0.5]);
+        assert_eq!(super::unpack_samples(&[1, 2, 3], &[16, 8], false).unwrap(), vec![0x0102, 3]);
+        assert_eq!(super::unpack_samples(&[1, 2, 3], &[16, 8], true).unwrap(), vec![0x0201, 3]);
+        // 2x1 gray image with an identity transfer function
+        let table: Vec<u16> = (0..256).map(|v| v * 257).collect();
+        let entries = |bits: u16| vec![
+            (0x0100, 3, 1, shorts(&[2])),
+            (0x0101, 3, 1, shorts(&[1])),
+            (0x0102, 3, 1, shorts(&[bits])),
+            (0x0111, 4, 1, 8u32.to_le_bytes().to_vec()),
+            (0x0117, 4, 1, 2u32.to_le_bytes().to_vec()),
+            (0x012D, 3, 256, shorts(&table)),
+        ];
+        let tiff = super::Tiff::from_reader(std::io::Cursor::new(tiff_with_strip(&[0, 51], &entries(8))), false).unwrap();
+        let ifd = &tiff.ifd[0];
+        let samples = tiff.read_region_samples(ifd, 0, 0, 2, 1).unwrap();
+        let linear = super::apply_transfer_function(&samples, &ifd.transfer_function().unwrap()).unwrap();
+        assert_eq!(linear, vec![0.0, 0.2]);
+        let tiff = super::Tiff::from_reader(std::io::Cursor::new(tiff_with_strip(&[0; 16], &entries(64))), false).unwrap();
+        assert!(tiff.ifd[0].transfer_function().is_err());
+    }
+    #[test]
+    fn from_reader() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("samples/BigTIFFMotorola.tif");
+        let bytes = std::fs::read(path).unwrap();
+        let tiff = super::Tiff::from_reader(std::io::Cursor::new(bytes), true).unwrap();
+        assert!(!tiff.header.is_le());
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    let ifd = tiff.read_frame(0).unwrap();
+                    assert_eq!(ifd.width().unwrap(), 64);
+                    assert_eq!(ifd.height().unwrap(), 64);
+                });
+            }
+        });
+    }
+    #[test]
+    fn borrowed_reader() {
+        let data = tiff_with_strip(&[1, 2, 3, 4], &[
+            (0x0100, 3, 1, shorts(&[2])),
+            (0x0101, 3, 1, shorts(&[2])),
+            (0x0102, 3, 1, shorts(&[8])),
+            (0x0111, 4, 1, 8u32.to_le_bytes().to_vec()),
+            (0x0117, 4, 1, 4u32.to_le_bytes().to_vec()),
+        ]);
+        let tiff = super::Tiff::from_reader(std::io::Cursor::new(&data[..]), false).unwrap();
+        assert_eq!(tiff.read_frame(0).unwrap().width().unwrap(), 2);
+        assert_eq!(tiff.read_region(&tiff.ifd[0], 1, 0, 1, 2).unwrap(), vec![2, 4]);
+        let mut cursor = std::io::Cursor::new(data.clone());
+        let tiff = super::Tiff::from_reader(&mut cursor, false).unwrap();
+        assert_eq!(tiff.read_image(&tiff.ifd[0]).unwrap(), vec![1, 2, 3, 4]);
+        let tiff = super::Tiff::from_buffer(&mut std::io::Cursor::new(&data), false).unwrap();
+        assert!(matches!(tiff.read_chunk(&tiff.ifd[0], 0), Err(super::TiffError::NoReader)));
+    }
+    #[test]
+    fn from_bytes() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("samples/BigTIFFMotorolaLongStrips.tif");
+        let bytes = std::fs::read(path).unwrap();
+        let tiff = super::Tiff::from_bytes(&bytes).unwrap();
+        let ifd = tiff.ifd[0].to_ifd().unwrap();
+        assert_eq!(ifd.width().unwrap(), 64);
+        let strip = tiff.ifd[0].strip(0).unwrap();
+        assert!(bytes.as_ptr_range().contains(&strip.as_ptr()));
+        assert!(tiff.ifd[0].strip(2).is_err());
+    }
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn from_mmap() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("samples/BigTIFFLong8Tiles.tif");
+        let tiff = super::Tiff::from_mmap(&path, true).unwrap();
+        let ifd = tiff.read_frame(0).unwrap();
+        assert_eq!(ifd.width().unwrap(), 64);
+        let mapped = tiff.mapped().unwrap();
+        std::thread::scope(|scope| {
+            for i in 0..4 {
+                let mapped = &mapped;
+                scope.spawn(move || assert!(!mapped.ifd[0].tile(i).unwrap().is_empty()));
+            }
+        });
+    }
+    struct CountingFetcher {
+        data: Vec<u8>,
+        requests: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+    }
+    impl super::RangeFetcher for CountingFetcher {
+        fn size(&mut self) -> Result<u64, super::TiffError> {
+            Ok(self.data.len() as u64)
+        }
+        fn fetch(&mut self, offset: u64, len: u64) -> Result<Vec<u8>, super::TiffError> {
+            self.requests.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(self.data[offset as usize..(offset + len) as usize].to_vec())
+        }
+    }
+    #[test]
+    fn range_reader() {
+        use std::sync::atomic::Ordering;
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("samples/BigTIFFSubIFD8.tif");
+        let requests = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let fetcher = CountingFetcher{data: std::fs::read(path).unwrap(), requests: requests.clone()};
+        let mut reader = super::RangeReader::with_cache(fetcher, 1024, 64);
+        reader.prefetch(&[(15572, 200), (15800, 100), (31324, 200)]).unwrap();
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+        let tiff = super::Tiff::from_reader(reader, false).unwrap();
+        let fetched = requests.load(Ordering::SeqCst);
+        assert_eq!(tiff.len(), 2);
+        tiff.read_frame(1).unwrap();
+        assert_eq!(requests.load(Ordering::SeqCst), fetched);
+        // read_chunks fetches all the tiles of a range reader up front
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("samples/BigTIFFLong8Tiles.tif");
+        let requests = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let fetcher = CountingFetcher{data: std::fs::read(path).unwrap(), requests: requests.clone()};
+        let tiff = super::Tiff::from_range_reader(super::RangeReader::with_cache(fetcher, 1024, 64), false).unwrap();
+        let ifd = tiff.read_frame(0).unwrap();
+        let fetched = requests.load(Ordering::SeqCst);
+        tiff.read_image(&ifd).unwrap();
+        assert_eq!(requests.load(Ordering::SeqCst), fetched + 1);
+    }
+    #[cfg(feature = "http")]
+    fn serve(data: Vec<u8>, ranges: bool) -> String {
+        use std::io::{BufRead, Write};
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/BigTIFF.tif", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                loop {
+                    let mut request = String::new();
+                    if reader.read_line(&mut request).unwrap_or(0) == 0 {
+                        break;
+                    }
+                    let mut range = None;
+                    loop {
+                        let mut line = String::new();
+                        reader.read_line(&mut line).unwrap();
+                        if line.trim().is_empty() {
+                            break;
+                        }
+                        if let Some(value) = line.to_ascii_lowercase().strip_prefix("range: bytes=") {
+                            let (start, end) = value.trim().split_once('-').unwrap();
+                            range = Some((start.parse::<usize>().unwrap(), end.parse::<usize>().unwrap()));
+                        }
+                    }
+                    if request.starts_with("HEAD") {
+                        write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n", data.len()).unwrap();
+                    } else if !ranges {
+                        write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n", data.len()).unwrap();
+                        stream.write_all(&data).unwrap();
+                    } else {
+                        let (start, end) = range.unwrap();
+                        let body = &data[start..=end.min(data.len() - 1)];
+                        write!(stream, "HTTP/1.1 206 Partial Content\r\nContent-Length: {}\r\n\r\n", body.len()).unwrap();
+                        stream.write_all(body).unwrap();
+                    }
+                }
+            }
+        });
+        url
+    }
+    #[cfg(feature = "http")]
+    #[test]
+    fn from_url() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("samples/BigTIFF.tif");
+        let data = std::fs::read(path).unwrap();
+        let tiff = super::Tiff::from_url(&serve(data.clone(), true), true).unwrap();
+        let ifd = tiff.read_frame(0).unwrap();
+        assert_eq!(ifd.width().unwrap(), 64);
+        let mut fetcher = super::range::HttpFetcher::new(&serve(data, false));
+        assert!(matches!(super::RangeFetcher::fetch(&mut fetcher, 0, 16), Err(super::TiffError::CannotFetchRange)));
+    }
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn async_tiff() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("samples/BigTIFFMotorolaLongStrips.tif");
+        let tiff = super::AsyncTiff::from_path(&path, true).await.unwrap();
+        assert_eq!(tiff.len(), 1);
+        let ifd = tiff.read_frame(0).await.unwrap();
+        assert_eq!(ifd.width().unwrap(), 64);
+        assert_eq!(ifd.bpp().unwrap(), vec![8, 8, 8]);
+        let path = path.with_file_name("BigTIFFLong8Tiles.tif");
+        let mut tiff = super::AsyncTiff::from_path(&path, true).await.unwrap();
+        tiff.defer_threshold = Some(8);
+        let ifd = tiff.read_frame(0).await.unwrap();
+        let tag = ifd.get_tag(super::ifd::TagID::ExtensionTag(super::ifd::Extension::TileOffsets)).unwrap();
+        assert!(!tag.is_loaded());
+        assert_eq!(tiff.load_tag(tag).await.unwrap().as_unsigned_integers().unwrap().len(), 4);
+        let raw = tiff.read_raw_tile(&ifd, 3).await.unwrap();
+        assert_eq!(raw.compression, 1);
+        assert_eq!(*tiff.read_chunk(&ifd, 3).await.unwrap(), raw.data);
+        let sync = super::Tiff::from_path(&path, true).unwrap();
+        let expected = sync.read_region(&sync.read_frame(0).unwrap(), 16, 16, 32, 32).unwrap();
+        assert_eq!(tiff.read_region(&ifd, 16, 16, 32, 32).await.unwrap(), expected);
+        tiff.set_cache_budget(1 << 20);
+        let tile = tiff.read_chunk(&ifd, 0).await.unwrap();
+        assert!(std::sync::Arc::ptr_eq(&tile, &tiff.read_chunk(&ifd, 0).await.unwrap()));
+        let path = path.with_file_name("BigTIFFSubIFD4.tif");
+        let file = tokio::io::BufReader::new(tokio::fs::File::open(&path).await.unwrap());
+        let tiff = super::AsyncTiff::from_reader_first(file, true).await.unwrap();
+        assert_eq!(tiff.len(), 1);
+        let mut iter = tiff.iter(true);
+        let mut positions: Vec<u64> = vec![];
+        while let Some(ifd) = iter.next().await {
+            positions.push(ifd.unwrap().pos);
+        }
+        assert_eq!(positions, vec![15572, 31324]);
+        let data = tiff_with_private_ifd(0x8825, &[(0x0002, 5, 3, [rational(33, 1), rational(51, 1), rational(54, 1)].concat())]);
+        let tiff = super::AsyncTiff::from_reader_with_threshold(std::io::Cursor::new(data), false, 4).await.unwrap();
+        assert!((tiff.ifd[0].latitude().unwrap() - 33.865).abs() < 1e-9);
+        let mut data = vec![b'I', b'I', 43, 0, 8, 0, 0, 0];
+        data.extend(16u64.to_le_bytes());
+        data.extend((1u64 << 42).to_le_bytes());
+        assert!(super::AsyncTiff::from_reader(std::io::Cursor::new(data), true).await.is_err());
+        let data = tiff_with_strip(&[], &[(0x0100, 3, 1, shorts(&[1])), (0x0101, 3, 1, shorts(&[1])), (0x8769, 4, 1, longs(&[1000]))]);
+        let tiff = super::AsyncTiff::from_reader(std::io::Cursor::new(data), false).await.unwrap();
+        assert!(tiff.ifd[0].exif_ifd.is_none());
+    }
+    #[test]
+    fn lazy_ifd() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("samples/BigTIFFSubIFD4.tif");
+        let tiff = super::Tiff::from_path_first(&path, false).unwrap();
+        assert_eq!(tiff.len(), 1);
+        let positions: Vec<u64> = tiff.iter(true).map(|ifd| ifd.unwrap().pos).collect();
+        assert_eq!(positions, vec![15572, 31324]);
+    }
+    #[test]
+    fn circular_ifd_chain() {
+        // the first IFD's next pointer leads back to itself
+        let mut data = tiff_with_strip(&[], &[(0x0100, 3, 1, shorts(&[1])), (0x0101, 3, 1, shorts(&[1]))]);
+        data[8 + 2 + 2 * 12..8 + 2 + 2 * 12 + 4].copy_from_slice(&8u32.to_le_bytes());
+        assert!(matches!(super::Tiff::from_bytes(&data), Err(super::TiffError::CircularIFDChain)));
+        assert!(matches!(super::Tiff::from_reader(std::io::Cursor::new(&data), false), Err(super::TiffError::CircularIFDChain)));
+        let tiff = super::Tiff::from_reader_first(std::io::Cursor::new(&data), false).unwrap();
+        let frames: Vec<_> = tiff.iter(false).collect();
+        assert_eq!(frames.len(), 2);
+        assert!(frames[0].is_ok());
+        assert!(matches!(frames[1], Err(super::TiffError::CircularIFDChain)));
+    }
+    #[test]
+    fn repeated_sub_ifd() {
+        // the SubIFD at 50 lists itself twice as its own SubIFDs
+        let data = tiff_with_private_ifd(0x014A, &[(0x014A, 4, 2, longs(&[50, 50]))]);
+        for skip in [false, true] {
+            let tiff = super::Tiff::from_reader(std::io::Cursor::new(&data), skip).unwrap();
+            assert_eq!(tiff.ifd[0].sub_ifd.len(), 1);
+            assert!(tiff.ifd[0].sub_ifd[0].sub_ifd.is_empty());
+        }
+        let view = super::Tiff::from_bytes(&data).unwrap();
+        assert!(view.ifd[0].sub_ifd[0].sub_ifd.is_empty());
+        // a BigTIFF IFD claiming 2^42 entries
+        let mut data = vec![b'I', b'I', 43, 0, 8, 0, 0, 0];
+        data.extend(16u64.to_le_bytes());
+        data.extend((1u64 << 42).to_le_bytes());
+        assert!(matches!(super::Tiff::from_reader(std::io::Cursor::new(&data), true), Err(super::TiffError::UnexpectedEndOfBuffer)));
+    }
+    #[test]
+    fn broken_child_pointer() {
+        let data = tiff_with_strip(&[], &[(0x0100, 3, 1, shorts(&[1])), (0x0101, 3, 1, shorts(&[1])), (0x8769, 4, 1, longs(&[1000]))]);
+        for skip in [false, true] {
+            let tiff = super::Tiff::from_reader(std::io::Cursor::new(&data), skip).unwrap();
+            assert!(tiff.ifd[0].exif_ifd.is_none());
+        }
+        assert!(super::Tiff::from_bytes(&data).unwrap().ifd[0].exif_ifd.is_none());
+    }
+    #[test]
+    fn deferred_tags() {
+        use super::ifd::{Baseline, TagID};
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("samples/BigTIFFLong8Tiles.tif");
+        let mut tiff = super::Tiff::from_path(&path, true).unwrap();
+        tiff.defer_threshold = Some(8);
+        let ifd = tiff.read_frame(0).unwrap();
+        assert_eq!(ifd.width().unwrap(), 64);
+        let tag = ifd.get_tag(TagID::BaselineTag(Baseline::BitsPerSample)).unwrap();
+        assert!(tag.deferred().is_none());
+        let tag = ifd.get_tag(TagID::ExtensionTag(super::ifd::Extension::TileOffsets)).unwrap();
+        assert!(!tag.is_loaded());
+        let offsets = tiff.load_tag(tag).unwrap().as_unsigned_integers().unwrap();
+        assert_eq!(offsets.len(), 4);
+        assert!(tag.is_loaded());
+    }
+    #[test]
+    fn deferred_accessors() {
+        use super::ifd::{Baseline, TagID};
+        // 1x2 RGB image in two strips: BitsPerSample and both strip arrays are out of line
+        let data = tiff_with_strip(&[1, 2, 3, 4, 5, 6], &[
+            (0x0100, 3, 1, shorts(&[1])),
+            (0x0101, 3, 1, shorts(&[2])),
+            (0x0102, 3, 3, shorts(&[8, 8, 8])),
+            (0x0106, 3, 1, shorts(&[2])),
+            (0x0111, 4, 2, longs(&[8, 11])),
+            (0x0115, 3, 1, shorts(&[3])),
+            (0x0116, 3, 1, shorts(&[1])),
+            (0x0117, 4, 2, longs(&[3, 3])),
+        ]);
+        let tiff = super::Tiff::from_reader_with_threshold(std::io::Cursor::new(data), false, 4).unwrap();
+        for ifd in [&tiff.ifd[0], &tiff.read_frame(0).unwrap()] {
+            assert_eq!(ifd.bpp().unwrap(), vec![8, 8, 8]);
+            assert!(!ifd.get_tag(TagID::BaselineTag(Baseline::StripOffsets)).unwrap().is_loaded());
+            assert_eq!(tiff.read_region(ifd, 0, 1, 1, 1).unwrap(), vec![4, 5, 6]);
+        }
+    }
+    #[test]
+    fn chunk_cache() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("samples/BigTIFFLong8Tiles.tif");
+        let mut tiff = super::Tiff::from_path(&path, true).unwrap();
+        tiff.set_cache_budget(2 * 32 * 32 * 3);
+        let ifd = tiff.read_frame(0).unwrap();
+        let tile = tiff.read_chunk(&ifd, 0).unwrap();
+        assert_eq!(tile.len(), 32 * 32 * 3);
+        assert!(std::sync::Arc::ptr_eq(&tile, &tiff.read_chunk(&ifd, 0).unwrap()));
+        tiff.read_chunk(&ifd, 1).unwrap();
+        tiff.read_chunk(&ifd, 2).unwrap();
+        assert!(!std::sync::Arc::ptr_eq(&tile, &tiff.read_chunk(&ifd, 0).unwrap()));
+        let region = tiff.read_region(&ifd, 16, 16, 32, 32).unwrap();
+        let strips = super::Tiff::from_path(&path.with_file_name("BigTIFF.tif"), true).unwrap();
+        let expected = strips.read_region(&strips.read_frame(0).unwrap(), 16, 16, 32, 32).unwrap();
+        assert_eq!(region, expected);
+    }
+    #[test]
+    fn raw_chunks() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("samples/BigTIFFLong8Tiles.tif");
+        let tiff = super::Tiff::from_path(&path, true).unwrap();
+        let ifd = tiff.read_frame(0).unwrap();
+        let tile = tiff.read_raw_tile(&ifd, 3).unwrap();
+        assert_eq!(tile.scheme(), Some(super::CompressionScheme::NoCompression));
+        assert!(tile.jpeg_tables.is_none());
+        assert_eq!(tile.data, *tiff.read_chunk(&ifd, 3).unwrap());
+        assert!(tiff.read_raw_strip(&ifd, 0).is_err());
+    }
+    #[test]
+    fn sub_ifd_tree() {
+        for name in ["samples/BigTIFFSubIFD4.tif", "samples/BigTIFFSubIFD8.tif"] {
+            let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(name);
+            let tiff = super::Tiff::from_path(&path, true).unwrap();
+            assert_eq!(tiff.len(), 2);
+            assert_eq!(tiff.ifd[0].sub_ifd.len(), 1);
+            let ifd = tiff.read_frame(0).unwrap();
+            let child = &ifd.sub_ifd[0];
+            assert!(child.width().unwrap() > 0);
+            assert_eq!(ifd.descendants().len(), 1 + child.descendants().len());
+            let bytes = std::fs::read(&path).unwrap();
+            let view = super::Tiff::from_bytes(&bytes).unwrap();
+            assert_eq!(view.ifd[0].sub_ifd[0].pos, child.pos);
+        }
+    }
+    // Appends a little-endian classic IFD of (id, kind, count, value bytes) entries,
+    // with out-of-line values right after it.
+    fn write_ifd(out: &mut Vec<u8>, entries: &[(u16, u16, u32, Vec<u8>)]) {
+        let mut data_pos = out.len() + 2 + entries.len() * 12 + 4;
+        let mut data: Vec<u8> = vec![];
+        out.extend((entries.len() as u16).to_le_bytes());
+        for (id, kind, count, value) in entries {
+            out.extend(id.to_le_bytes());
+            out.extend(kind.to_le_bytes());
+            out.extend(count.to_le_bytes());
+            if value.len() <= 4 {
+                let mut inline = value.clone();
+                inline.resize(4, 0);
+                out.extend(inline);
+            } else {
+                out.extend((data_pos as u32).to_le_bytes());
+                data.extend(value);
+                data_pos += value.len();
+            }
+        }
+        out.extend(0u32.to_le_bytes());
+        out.extend(data);
+    }
+    // Little-endian classic TIFF with a 1x1 first IFD whose `pointer` tag leads to an
+    // IFD made of `entries`.
+    fn tiff_with_private_ifd(pointer: u16, entries: &[(u16, u16, u32, Vec<u8>)]) -> Vec<u8> {
+        let mut out = vec![b'I', b'I', 42, 0, 8, 0, 0, 0];
+        // three inline entries: the private IFD starts at 8 + 2 + 3 * 12 + 4
+        write_ifd(&mut out, &[
+            (0x0100, 3, 1, vec![1, 0]),
+            (0x0101, 3, 1, vec![1, 0]),
+            (pointer, 4, 1, 50u32.to_le_bytes().to_vec()),
+        ]);
+        write_ifd(&mut out, entries);
+        out
+    }
+    // Little-endian classic TIFF with `pixels` stored at 8 and a first IFD of `entries`
+    // right after them.
+    fn tiff_with_strip(pixels: &[u8], entries: &[(u16, u16, u32, Vec<u8>)]) -> Vec<u8> {
+        let mut out = vec![b'I', b'I', 42, 0];
+        out.extend((8 + pixels.len() as u32).to_le_bytes());
+        out.extend(pixels);
+        write_ifd(&mut out, entries);
+        out
+    }
+    fn rational(numerator: u32, denominator: u32) -> Vec<u8> {
+        [numerator.to_le_bytes(), denominator.to_le_bytes()].concat()
+    }
+    #[test]
+    fn exif_ifd() {
+        let data = tiff_with_private_ifd(0x8769, &[
+            (0x829A, 5, 1, rational(1, 250)),
+            (0x829D, 5, 1, rational(28, 10)),
+            (0x8827, 3, 1, vec![0x90, 0x01]),
+            (0x9003, 2, 20, b"2024:05:01 12:30:00\0".to_vec()),
+            (0xA434, 2, 8, b"XF23mm\0\0".to_vec()),
+        ]);
+        let tiff = super::Tiff::from_reader(std::io::Cursor::new(data.clone()), false).unwrap();
+        let ifd = &tiff.ifd[0];
+        assert!(ifd.exif().is_ok());
+        assert_eq!(ifd.exposure_time().unwrap(), 0.004);
+        assert_eq!(ifd.f_number().unwrap(), 2.8);
+        assert_eq!(ifd.iso().unwrap(), 400);
+        assert_eq!(ifd.date_time_original().unwrap(), "2024:05:01 12:30:00");
+        assert_eq!(ifd.lens_model().unwrap(), "XF23mm");
+        assert!(ifd.focal_length().is_err());
+        let view = super::Tiff::from_bytes(&data).unwrap();
+        assert_eq!(view.ifd[0].to_ifd().unwrap().iso().unwrap(), 400);
+    }
+    #[test]
+    fn gps_ifd() {
+        let data = tiff_with_private_ifd(0x8825, &[
+            (0x0001, 2, 2, b"S\0".to_vec()),
+            (0x0002, 5, 3, [rational(33, 1), rational(51, 1), rational(54, 1)].concat()),
+            (0x0003, 2, 2, b"E\0".to_vec()),
+            (0x0004, 5, 3, [rational(151, 1), rational(12, 1), rational(36, 1)].concat()),
+            (0x0005, 1, 1, vec![1]),
+            (0x0006, 5, 1, rational(25, 2)),
+        ]);
+        let tiff = super::Tiff::from_reader(std::io::Cursor::new(data.clone()), false).unwrap();
+        let ifd = &tiff.ifd[0];
+        assert!((ifd.latitude().unwrap() + 33.865).abs() < 1e-9);
+        assert!((ifd.longitude().unwrap() - 151.21).abs() < 1e-9);
+        assert_eq!(ifd.altitude().unwrap(), -12.5);
+        // GPS values are private tags until retagged, and must not be deferred as such
+        let tiff = super::Tiff::from_reader_with_threshold(std::io::Cursor::new(data.clone()), false, 4).unwrap();
+        assert!((tiff.ifd[0].latitude().unwrap() + 33.865).abs() < 1e-9);
+        let view = super::Tiff::from_bytes(&data).unwrap();
+        assert_eq!(view.ifd[0].to_ifd().unwrap().altitude().unwrap(), -12.5);
+    }
+    #[test]
+    fn interop_and_maker_note() {
+        let mut note = b"Nikon\0\x02\x10\0\0II*\0\x08\0\0\0".to_vec();
+        write_ifd(&mut note, &[(0x0001, 7, 4, b"0210".to_vec())]);
+        // EXIF IFD at 50 with two entries, the MakerNote value at 80, then the interop IFD
+        let note_offset = 50 + 2 + 2 * 12 + 4;
+        let interop_offset = (note_offset + note.len()) as u32;
+        let mut data = tiff_with_private_ifd(0x8769, &[
+            (0x927C, 7, note.len() as u32, note.clone()),
+            (0xA005, 4, 1, interop_offset.to_le_bytes().to_vec()),
+        ]);
+        write_ifd(&mut data, &[(0x0001, 2, 4, b"R98\0".to_vec())]);
+        let tiff = super::Tiff::from_reader(std::io::Cursor::new(data), false).unwrap();
+        assert_eq!(tiff.ifd[0].interoperability_index().unwrap(), "R98");
+        let maker_note = tiff.maker_note(0).unwrap();
+        assert_eq!(maker_note.offset, note_offset as u64);
+        assert_eq!(maker_note.data, note);
+        assert_eq!(maker_note.vendor, super::MakerNoteVendor::Nikon);
+        let ifd = maker_note.ifd.unwrap();
+        assert_eq!(ifd.get_tag(super::ifd::TagID::PrivateTag(1)).unwrap().data().unwrap().as_string().unwrap(), "0210");
+    }
+    // Little-endian classic TIFF whose first IFD has a Make and an EXIF IFD holding the
+    // MakerNote built by `note` from the file offset it is stored at.
+    fn tiff_with_maker_note(make: &str, note: impl Fn(usize) -> Vec<u8>) -> Vec<u8> {
+        let make = format!("{}\0", make).into_bytes();
+        let exif_offset = 8 + 2 + 4 * 12 + 4 + make.len();
+        let note = note(exif_offset + 2 + 12 + 4);
+        let mut data = vec![b'I', b'I', 42, 0, 8, 0, 0, 0];
+        write_ifd(&mut data, &[
+            (0x0100, 3, 1, shorts(&[1])),
+            (0x0101, 3, 1, shorts(&[1])),
+            (0x010F, 2, make.len() as u32, make),
+            (0x8769, 4, 1, (exif_offset as u32).to_le_bytes().to_vec()),
+        ]);
+        write_ifd(&mut data, &[(0x927C, 7, note.len() as u32, note)]);
+        data
+    }
+    #[test]
+    fn maker_note_vendors() {
+        let model = |data: Vec<u8>, id: u16| {
+            let tiff = super::Tiff::from_reader(std::io::Cursor::new(data), false).unwrap();
+            let maker_note = tiff.maker_note(0).unwrap();
+            let ifd = maker_note.ifd.unwrap();
+            let model = ifd.get_tag(super::ifd::TagID::PrivateTag(id)).unwrap().data().unwrap().as_string().unwrap();
+            (maker_note.vendor, model)
+        };
+        // bare IFD, its values at file offsets
+        let canon = tiff_with_maker_note("Canon", |offset| {
+            let mut note = vec![0u8; offset];
+            write_ifd(&mut note, &[(0x0006, 2, 8, b"EOS 5D\0\0".to_vec())]);
+            note.split_off(offset)
+        });
+        assert_eq!(model(canon, 0x0006), (super::MakerNoteVendor::Canon, String::from("EOS 5D")));
+        // "OLYMPUS\0II" + version, offsets relative to the start of the note
+        let olympus = tiff_with_maker_note("OLYMPUS IMAGING CORP.", |_| {
+            let mut note = b"OLYMPUS\0II\x03\0".to_vec();
+            write_ifd(&mut note, &[(0x0207, 2, 6, b"D4040\0".to_vec())]);
+            note
+        });
+        assert_eq!(model(olympus, 0x0207), (super::MakerNoteVendor::Olympus, String::from("D4040")));
+        // old 8 byte signature, offsets relative to the file
+        let olympus = tiff_with_maker_note("OLYMPUS OPTICAL CO.,LTD", |offset| {
+            let mut note = vec![0u8; offset];
+            note.extend(b"OLYMP\0\x01\0");
+            write_ifd(&mut note, &[(0x0207, 2, 6, b"C5050\0".to_vec())]);
+            note.split_off(offset)
+        });
+        assert_eq!(model(olympus, 0x0207), (super::MakerNoteVendor::Olympus, String::from("C5050")));
+    }
+    fn shorts(values: &[u16]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_le_bytes()).collect()
+    }
+    fn longs(values: &[u32]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_le_bytes()).collect()
+    }
+    fn doubles(values: &[f64]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_le_bytes()).collect()
+    }
+    // Little-endian classic TIFF made of a single IFD
+    #[test]
+    fn geo_keys() {
+        let directory = [
+            1, 1, 0, 5,
+            1024, 0, 1, 1,
+            1025, 0, 1, 2,
+            1026, 34737, 8, 0,
+            2057, 34736, 1, 0,
+            3072, 0, 1, 32633,
+        ];
+        let data = tiff_with_strip(&[], &[
+            (0x0100, 3, 1, shorts(&[1])),
+            (0x0101, 3, 1, shorts(&[1])),
+            (0x87AF, 3, directory.len() as u32, shorts(&directory)),
+            (0x87B0, 12, 1, doubles(&[6378137.0])),
+            (0x87B1, 2, 10, b"UTM 33N|\0\0".to_vec()),
+        ]);
+        let tiff = super::Tiff::from_reader(std::io::Cursor::new(data), false).unwrap();
+        let keys = tiff.ifd[0].geo_keys().unwrap();
+        assert_eq!(keys.keys.len(), 5);
+        assert_eq!(keys.model_type().unwrap(), super::ModelType::Projected);
+        assert_eq!(keys.raster_type().unwrap(), super::RasterType::PixelIsPoint);
+        assert_eq!(keys.epsg().unwrap(), 32633);
+        assert_eq!(keys.citation().unwrap(), "UTM 33N");
+        assert_eq!(keys.get_double(super::GeoKeyId::GeogSemiMajorAxis).unwrap(), 6378137.0);
+    }
+    #[test]
+    fn geo_transform() {
+        let georeferenced = |raster_type: u16| tiff_with_strip(&[], &[
+            (0x0100, 3, 1, shorts(&[100])),
+            (0x0101, 3, 1, shorts(&[50])),
+            (0x830E, 12, 3, doubles(&[10.0, 10.0, 0.0])),
+            (0x8482, 12, 6, doubles(&[0.0, 0.0, 0.0, 500000.0, 4000000.0, 0.0])),
+            (0x87AF, 3, 8, shorts(&[1, 1, 0, 1, 1025, 0, 1, raster_type])),
+        ]);
+        let tiff = super::Tiff::from_reader(std::io::Cursor::new(georeferenced(1)), false).unwrap();
+        let ifd = &tiff.ifd[0];
+        assert_eq!(ifd.bounding_box().unwrap(), (500000.0, 3999500.0, 501000.0, 4000000.0));
+        let transform = ifd.geo_transform().unwrap();
+        assert_eq!(transform.pixel_to_model(10.0, 5.0), (500100.0, 3999950.0));
+        assert_eq!(transform.model_to_pixel(500100.0, 3999950.0).unwrap(), (10.0, 5.0));
+        // the tiepoint is the center of the first pixel
+        let tiff = super::Tiff::from_reader(std::io::Cursor::new(georeferenced(2)), false).unwrap();
+        assert_eq!(tiff.ifd[0].geo_transform().unwrap().pixel_to_model(0.0, 0.0), (499995.0, 4000005.0));
+    }
+    #[test]
+    fn gdal_metadata_and_nodata() {
+        let xml = concat!(
+            "<GDALMetadata>\n",
+            "  <Item name=\"AREA_OR_POINT\">Area</Item>\n",
+            "  <Item name=\"DESCRIPTION\" sample=\"0\" role=\"description\">elevation</Item>\n",
+            "  <Item name=\"SCALE\" sample=\"0\" role=\"scale\">0.5</Item>\n",
+            "  <Item name=\"OFFSET\" sample=\"0\" role=\"offset\">-10</Item>\n",
+            "  <Item name=\"SOURCE\" sample=\"0\">survey &amp; lidar</Item>\n",
+            "</GDALMetadata>\0",
+        );
+        // four signed 16 bit samples stored right after the header, the IFD follows at 16
+        let mut data = vec![b'I', b'I', 42, 0, 16, 0, 0, 0];
+        data.extend(shorts(&[100, -9999i16 as u16, 7, -9999i16 as u16]));
+        write_ifd(&mut data, &[
+            (0x0100, 3, 1, shorts(&[4])),
+            (0x0101, 3, 1, shorts(&[1])),
+            (0x0102, 3, 1, shorts(&[16])),
+            (0x0111, 4, 1, 8u32.to_le_bytes().to_vec()),
+            (0x0116, 3, 1, shorts(&[1])),
+            (0x0117, 4, 1, 8u32.to_le_bytes().to_vec()),
+            (0x0153, 3, 1, shorts(&[2])),
+            (0xA480, 2, xml.len() as u32, xml.as_bytes().to_vec()),
+            (0xA481, 2, 6, b"-9999\0".to_vec()),
+        ]);
+        let tiff = super::Tiff::from_reader(std::io::Cursor::new(data), false).unwrap();
+        let ifd = &tiff.ifd[0];
+        let metadata = ifd.gdal_metadata().unwrap();
+        assert_eq!(metadata.get("AREA_OR_POINT"), Some("Area"));
+        assert_eq!(metadata.band(0).get("SOURCE").map(|v| v.as_str()), Some("survey & lidar"));
+        assert_eq!((metadata.scale(0), metadata.offset(0)), (Some(0.5), Some(-10.0)));
+        assert_eq!(ifd.gdal_nodata().unwrap(), super::NoData::Signed(-9999));
+        let (_, mask) = tiff.read_region_masked(ifd, 0, 0, 4, 1).unwrap();
+        assert_eq!(mask, vec![true, false, true, false]);
+        let floats: Vec<u8> = [0.1f32, 0.2, f32::NAN].iter().flat_map(|v| v.to_be_bytes()).collect();
+        let nodata = super::NoData::parse("0.1", 3).unwrap();
+        assert_eq!(nodata.mask(&floats, 32, 1, false).unwrap(), vec![false, true, true]);
+        let nodata = super::NoData::parse("nan", 3).unwrap();
+        assert_eq!(nodata.mask(&floats, 32, 1, false).unwrap(), vec![true, true, false]);
+        let wide = doubles(&[0.1, 0.1f32 as f64]);
+        assert_eq!(super::NoData::parse("0.1", 3).unwrap().mask(&wide, 64, 1, true).unwrap(), vec![false, true]);
+    }
+    // 64x64 image in four 32x32 tiles with one 32x32 overview, laid out as a COG
+    // unless the main image data is written first.
+    fn cog(overview_first: bool) -> Vec<u8> {
+        let ghost = b"GDAL_STRUCTURAL_METADATA_SIZE=000000 bytes\n";
+        let ifd0_pos = 8 + ghost.len();
+        let ifd1_pos = ifd0_pos + 2 + 6 * 12 + 4 + 32;
+        let data_pos = (ifd1_pos + 2 + 7 * 12 + 4) as u32;
+        let (overview, main) = if overview_first { (data_pos, data_pos + 4) } else { (data_pos + 16, data_pos) };
+        let mut data = vec![b'I', b'I', 42, 0, ifd0_pos as u8, 0, 0, 0];
+        data.extend(ghost);
+        write_ifd(&mut data, &[
+            (0x0100, 3, 1, shorts(&[64])),
+            (0x0101, 3, 1, shorts(&[64])),
+            (0x0142, 3, 1, shorts(&[32])),
+            (0x0143, 3, 1, shorts(&[32])),
+            (0x0144, 4, 4, longs(&[main, main + 4, main + 8, main + 12])),
+            (0x0145, 4, 4, longs(&[4, 4, 4, 4])),
+        ]);
+        data[ifd0_pos + 2 + 6 * 12..ifd0_pos + 2 + 6 * 12 + 4].copy_from_slice(&(ifd1_pos as u32).to_le_bytes());
+        write_ifd(&mut data, &[
+            (0x00FE, 4, 1, longs(&[1])),
+            (0x0100, 3, 1, shorts(&[32])),
+            (0x0101, 3, 1, shorts(&[32])),
+            (0x0142, 3, 1, shorts(&[32])),
+            (0x0143, 3, 1, shorts(&[32])),
+            (0x0144, 4, 1, longs(&[overview])),
+            (0x0145, 4, 1, longs(&[4])),
+        ]);
+        data.extend([0u8; 20]);
+        data
+    }
+    #[test]
+    fn cog_validation() {
+        let tiff = super::Tiff::from_reader(std::io::Cursor::new(cog(true)), false).unwrap();
+        let report = tiff.validate_cog().unwrap();
+        assert!(report.is_valid(), "{:?}", report.findings);
+        assert!(report.findings.is_empty());
+        let tiff = super::Tiff::from_reader(std::io::Cursor::new(cog(false)), false).unwrap();
+        let report = tiff.validate_cog().unwrap();
+        assert!(!report.is_valid());
+        assert_eq!(report.findings.len(), 1);
+        assert_eq!(report.findings[0].rule, super::CogRule::DataOrder);
+        assert_eq!(report.findings[0].ifd, Some(1));
+    }
+    #[test]
+    fn pyramid_levels() {
+        let tiff = super::Tiff::from_reader(std::io::Cursor::new(cog(true)), false).unwrap();
+        let pyramid = tiff.pyramid().unwrap();
+        assert_eq!(pyramid.levels.len(), 2);
+        assert_eq!((pyramid.main().width, pyramid.levels[1].scale), (64, 2.0));
+        assert_eq!(pyramid.best_level(1.5).scale, 1.0);
+        assert_eq!(pyramid.best_level(3.0).index, 1);
+        assert_eq!(pyramid.best_level_for_size(16, 16).width, 32);
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("samples/BigTIFFSubIFD8.tif");
+        let tiff = super::Tiff::from_path(&path, true).unwrap();
+        let pyramid = tiff.pyramid().unwrap();
+        // the half size SubIFD of the first image is its overview
+        assert_eq!(pyramid.levels.len(), 2);
+        assert_eq!((pyramid.levels[1].index, pyramid.levels[1].sub_ifd, pyramid.levels[1].scale), (0, Some(0), 2.0));
+        // an unflagged overview with the main image's tile size still counts
+        let mut data = cog(true);
+        let flag = 8 + 43 + 2 + 6 * 12 + 4 + 32 + 2 + 8;
+        data[flag] = 0;
+        let tiff = super::Tiff::from_reader(std::io::Cursor::new(data), false).unwrap();
+        assert!(!tiff.ifd[1].is_reduced_resolution());
+        assert_eq!(tiff.pyramid().unwrap().levels.len(), 2);
+        // an unrelated half size page of a stripped file does not
+        let mut data = tiff_with_strip(&[0; 8], &[
+            (0x0100, 3, 1, shorts(&[4])),
+            (0x0101, 3, 1, shorts(&[2])),
+            (0x0111, 4, 1, 8u32.to_le_bytes().to_vec()),
+            (0x0117, 4, 1, 1u32.to_le_bytes().to_vec()),
+        ]);
+        let next = data.len() - 4;
+        let page = data.len() as u32;
+        data[next..].copy_from_slice(&page.to_le_bytes());
+        write_ifd(&mut data, &[
+            (0x0100, 3, 1, shorts(&[2])),
+            (0x0101, 3, 1, shorts(&[1])),
+            (0x0111, 4, 1, 8u32.to_le_bytes().to_vec()),
+            (0x0117, 4, 1, 1u32.to_le_bytes().to_vec()),
+        ]);
+        let tiff = super::Tiff::from_reader(std::io::Cursor::new(data), false).unwrap();
+        assert_eq!(tiff.len(), 2);
+        assert_eq!(tiff.pyramid().unwrap().levels.len(), 1);
+        // a main image without pixels leaves no level
+        let data = tiff_with_strip(&[], &[(0x0100, 3, 1, shorts(&[0])), (0x0101, 3, 1, shorts(&[1]))]);
+        let tiff = super::Tiff::from_reader(std::io::Cursor::new(data), false).unwrap();
+        assert!(matches!(tiff.pyramid(), Err(super::TiffError::CannotFindTag)));
+    }
+    #[test]
+    fn transparency_mask() {
+        // 8x2 image strip at 8, its 1 bit mask strip at 24, IFDs at 26 and 116
+        let mut data = vec![b'I', b'I', 42, 0, 26, 0, 0, 0];
+        data.extend([7u8; 16]);
+        data.extend([0b1011_0000, 0b0000_1111]);
+        write_ifd(&mut data, &[
+            (0x0100, 3, 1, shorts(&[8])),
+            (0x0101, 3, 1, shorts(&[2])),
+            (0x0102, 3, 1, shorts(&[8])),
+            (0x0106, 3, 1, shorts(&[1])),
+            (0x0111, 3, 1, shorts(&[8])),
+            (0x0116, 3, 1, shorts(&[2])),
+            (0x0117, 3, 1, shorts(&[16])),
+        ]);
+        data[112..116].copy_from_slice(&116u32.to_le_bytes());
+        write_ifd(&mut data, &[
+            (0x00FE, 4, 1, 4u32.to_le_bytes().to_vec()),
+            (0x0100, 3, 1, shorts(&[8])),
+            (0x0101, 3, 1, shorts(&[2])),
+            (0x0102, 3, 1, shorts(&[1])),
+            (0x0106, 3, 1, shorts(&[4])),
+            (0x0111, 3, 1, shorts(&[24])),
+            (0x0116, 3, 1, shorts(&[2])),
+            (0x0117, 3, 1, shorts(&[2])),
+        ]);
+        let tiff = super::Tiff::from_reader(std::io::Cursor::new(data), false).unwrap();
+        assert_eq!(tiff.masks().unwrap(), vec![Some(1), None]);
+        let pyramid = tiff.pyramid().unwrap();
+        assert_eq!(pyramid.levels.len(), 1);
+        let main = pyramid.main();
+        let mask = main.mask.as_ref().unwrap();
+        assert_eq!(tiff.read_region(&main.ifd, 2, 0, 4, 2).unwrap().len(), 8);
+        assert_eq!(tiff.read_mask_region(mask, 2, 0, 4, 2).unwrap(),
+            vec![true, true, false, false, false, false, true, true]);
+    }
 }
@@ -0,0 +1,85 @@
+#![allow(dead_code)]
+
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use crate::{TiffError, Header, IFD};
+use crate::ifd::TagID;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MakerNoteVendor {
+    Nikon,
+    Canon,
+    Olympus,
+    Unknown,
+}
+
+// The EXIF MakerNote as stored, plus its IFD for vendors whose notes are TIFF structured.
+// Tags of that IFD keep their vendor numbering as `TagID::PrivateTag`.
+#[derive(Debug)]
+pub struct MakerNote {
+    pub offset: u64,
+    pub data: Vec<u8>,
+    pub vendor: MakerNoteVendor,
+    pub ifd: Option<IFD>,
+}
+impl MakerNote {
+    // `ifd` is an image IFD with its EXIF IFD attached; `buffer` is the whole file.
+    pub fn from_ifd<R: Read + Seek>(ifd: &IFD, buffer: &mut R, le: bool) -> Result<Self, TiffError> {
+        let tag = ifd.maker_note_tag()?;
+        let data = tag.load(buffer, le)?.as_unsigned_integers()?.iter().map(|v| *v as u8).collect::<Vec<u8>>();
+        // MakerNotes are always longer than 4 bytes, so the value is out of line
+        let offset = match (tag.offset(), tag.deferred()) {
+            (Some(offset), _) => offset,
+            (None, Some(deferred)) => deferred.offset,
+            (None, None) => return Err(TiffError::UnexpectedBufferSize),
+        };
+        let make = ifd.make().unwrap_or_default();
+        let vendor = if data.starts_with(b"Nikon\0") {
+            MakerNoteVendor::Nikon
+        } else if data.starts_with(b"OLYMP") {
+            MakerNoteVendor::Olympus
+        } else if make.starts_with("Canon") {
+            MakerNoteVendor::Canon
+        } else {
+            MakerNoteVendor::Unknown
+        };
+        // vendor layouts vary between models, so a note that does not parse is still returned
+        let ifd = Self::parse_ifd(&vendor, &data, offset, buffer, le).ok().map(|mut ifd| {
+            ifd.retag(TagID::to_private);
+            ifd
+        });
+        Ok(Self{offset, data, vendor, ifd})
+    }
+    fn parse_ifd<R: Read + Seek>(vendor: &MakerNoteVendor, data: &[u8], offset: u64, buffer: &mut R, le: bool) -> Result<IFD, TiffError> {
+        match vendor {
+            // "Nikon\0" + version, then a complete TIFF header; offsets are relative to it
+            MakerNoteVendor::Nikon if data.get(6) == Some(&2) => {
+                let mut note = Cursor::new(data.get(10..).ok_or(TiffError::UnexpectedEndOfBuffer)?);
+                let header = Header::from_buffer(&mut note)?;
+                note.seek(SeekFrom::Start(header.first_ifd)).or(Err(TiffError::UnexpectedEndOfBuffer))?;
+                IFD::from_buffer(&mut note, false, header.is_le(), false)
+            },
+            // "OLYMPUS\0" + byte order + version, offsets are relative to the note
+            MakerNoteVendor::Olympus if data.starts_with(b"OLYMPUS\0") => {
+                let le = match data.get(8..10) {
+                    Some(b"II") => true,
+                    Some(b"MM") => false,
+                    _ => return Err(TiffError::UnexpectedEndOfBuffer),
+                };
+                let mut note = Cursor::new(data);
+                note.seek(SeekFrom::Start(12)).or(Err(TiffError::UnexpectedEndOfBuffer))?;
+                IFD::from_buffer(&mut note, false, le, false)
+            },
+            // Nikon type 1 and old Olympus: 8 byte signature, offsets are relative to the file
+            MakerNoteVendor::Nikon | MakerNoteVendor::Olympus => {
+                buffer.seek(SeekFrom::Start(offset + 8)).or(Err(TiffError::UnexpectedEndOfBuffer))?;
+                IFD::from_buffer(buffer, false, le, false)
+            },
+            // a bare IFD with offsets relative to the file
+            MakerNoteVendor::Canon => {
+                buffer.seek(SeekFrom::Start(offset)).or(Err(TiffError::UnexpectedEndOfBuffer))?;
+                IFD::from_buffer(buffer, false, le, false)
+            },
+            MakerNoteVendor::Unknown => Err(TiffError::CannotFindTag),
+        }
+    }
+}
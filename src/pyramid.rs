@@ -0,0 +1,165 @@
+#![allow(dead_code)]
+
+use crate::{TiffError, Tiff, IFD};
+
+#[derive(Debug)]
+pub struct PyramidLevel {
+    // index into the top level IFD chain, and into its SubIFDs for levels stored there
+    pub index: usize,
+    pub sub_ifd: Option<usize>,
+    pub width: u64,
+    pub height: u64,
+    // full resolution pixels per level pixel, 1.0 for the main image
+    pub scale: f64,
+    pub ifd: IFD,
+    // transparency mask of the same size, see `associate_masks`
+    pub mask: Option<IFD>,
+}
+impl PyramidLevel {
+    pub fn is_tiled(&self) -> bool {
+        self.ifd.is_tiled()
+    }
+}
+
+fn is_mask_of(mask: &IFD, image: &IFD) -> bool {
+    mask.is_transparency_mask()
+        && mask.width().ok() == image.width().ok()
+        && mask.height().ok() == image.height().ok()
+}
+
+fn tiling(ifd: &IFD) -> Option<(u64, u64)> {
+    if !ifd.is_tiled() {
+        return None;
+    }
+    Some((ifd.tile_width().ok()?, ifd.tile_length().ok()?))
+}
+
+// For each IFD of a list (an IFD chain or the SubIFDs of one IFD), the index of its
+// transparency mask: an unclaimed mask of the same size, preferably the first one after it.
+pub fn associate_masks(ifds: &[IFD]) -> Vec<Option<usize>> {
+    let mut masks: Vec<Option<usize>> = vec![None; ifds.len()];
+    let mut claimed = vec![false; ifds.len()];
+    for (index, image) in ifds.iter().enumerate() {
+        if image.is_mask() {
+            continue;
+        }
+        let matches: Vec<usize> = (0..ifds.len()).filter(|i| !claimed[*i] && is_mask_of(&ifds[*i], image)).collect();
+        let mask = matches.iter().find(|i| **i > index).or(matches.first()).copied();
+        if let Some(mask) = mask {
+            claimed[mask] = true;
+        }
+        masks[index] = mask;
+    }
+    masks
+}
+
+// The main image and its reduced resolution versions, from the largest to the smallest.
+#[derive(Debug)]
+pub struct Pyramid {
+    pub levels: Vec<PyramidLevel>,
+}
+impl Pyramid {
+    // Levels are top level IFDs or SubIFDs of the main image that are not masks, and are
+    // either flagged reduced resolution in NewSubfileType or, in tiled files, smaller with
+    // the same aspect and tile size.
+    pub fn from_tiff(tiff: &Tiff) -> Result<Self, TiffError> {
+        let mut frames: Vec<IFD> = vec![];
+        for index in 0..tiff.len() {
+            frames.push(tiff.read_frame(index)?);
+        }
+        let main = frames.iter().position(|ifd| !ifd.is_mask() && !ifd.is_reduced_resolution()).ok_or(TiffError::CannotFindTag)?;
+        let (main_width, main_height) = (frames[main].width()?, frames[main].height()?);
+        let main_tiling = tiling(&frames[main]);
+        let is_level = |ifd: &IFD| -> Result<bool, TiffError> {
+            if ifd.is_mask() {
+                return Ok(false);
+            }
+            if ifd.is_reduced_resolution() {
+                return Ok(true);
+            }
+            // unflagged overviews are only recognized in tiled files, with the main image's tile size
+            if main_tiling.is_none() || tiling(ifd) != main_tiling {
+                return Ok(false);
+            }
+            let (width, height) = (ifd.width()?, ifd.height()?);
+            // rounding of odd sizes changes the aspect by up to a pixel
+            let expected = main_height as f64 * width as f64 / main_width as f64;
+            Ok(width < main_width && height < main_height && (height as f64 - expected).abs() <= 1.0)
+        };
+
+        let masks = associate_masks(&frames);
+        let mut frames: Vec<Option<IFD>> = frames.into_iter().map(Some).collect();
+        let mut candidates: Vec<(usize, Option<usize>, IFD, Option<IFD>)> = vec![];
+        for index in 0..frames.len() {
+            let is_candidate = match &frames[index] {
+                Some(ifd) => index == main || is_level(ifd)?,
+                None => false,
+            };
+            if !is_candidate {
+                continue;
+            }
+            let mut mask = masks[index].and_then(|mask| frames[mask].take());
+            let mut ifd = match frames[index].take() {
+                Some(ifd) => ifd,
+                None => continue,
+            };
+            if index == main {
+                let sub_ifds = std::mem::take(&mut ifd.sub_ifd);
+                let sub_masks = associate_masks(&sub_ifds);
+                let mut sub_ifds: Vec<Option<IFD>> = sub_ifds.into_iter().map(Some).collect();
+                for sub_index in 0..sub_ifds.len() {
+                    let is_sub_level = match &sub_ifds[sub_index] {
+                        Some(sub_ifd) => is_level(sub_ifd)?,
+                        None => false,
+                    };
+                    if !is_sub_level {
+                        continue;
+                    }
+                    let sub_mask = sub_masks[sub_index].and_then(|mask| sub_ifds[mask].take());
+                    if let Some(sub_ifd) = sub_ifds[sub_index].take() {
+                        candidates.push((index, Some(sub_index), sub_ifd, sub_mask));
+                    }
+                }
+                // a full size mask may also be stored as a SubIFD of the main image
+                if mask.is_none() {
+                    mask = sub_ifds.iter_mut()
+                        .find(|sub_ifd| sub_ifd.as_ref().is_some_and(|sub_ifd| is_mask_of(sub_ifd, &ifd)))
+                        .and_then(Option::take);
+                }
+            }
+            candidates.push((index, None, ifd, mask));
+        }
+        let mut levels: Vec<PyramidLevel> = vec![];
+        for (index, sub_ifd, ifd, mask) in candidates {
+            let (width, height) = (ifd.width()?, ifd.height()?);
+            if width == 0 || height == 0 {
+                continue;
+            }
+            let scale = main_width as f64 / width as f64;
+            levels.push(PyramidLevel{index, sub_ifd, width, height, scale, ifd, mask});
+        }
+        // an empty main image is skipped above, and a pyramid without it has no main level
+        if !levels.iter().any(|level| level.index == main && level.sub_ifd.is_none()) {
+            return Err(TiffError::CannotFindTag);
+        }
+        levels.sort_by(|a, b| a.scale.total_cmp(&b.scale));
+        Ok(Self{levels})
+    }
+    // `from_tiff` fails when the main image has no level, so `levels` is never empty.
+    pub fn main(&self) -> &PyramidLevel {
+        &self.levels[0]
+    }
+    // The coarsest level that still has at least the requested resolution, i.e. the
+    // largest scale not above `scale` (full resolution pixels per output pixel).
+    pub fn best_level(&self, scale: f64) -> &PyramidLevel {
+        self.levels.iter()
+            .rfind(|level| level.scale <= scale)
+            .unwrap_or(self.main())
+    }
+    // Best level for drawing the whole image into a `width` x `height` output.
+    pub fn best_level_for_size(&self, width: u64, height: u64) -> &PyramidLevel {
+        let main = self.main();
+        let scale = (main.width as f64 / width.max(1) as f64).min(main.height as f64 / height.max(1) as f64);
+        self.best_level(scale)
+    }
+}
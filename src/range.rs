@@ -0,0 +1,189 @@
+#![allow(dead_code)]
+
+use std::collections::{HashMap, VecDeque};
+use std::io::{Read, Seek, SeekFrom};
+use crate::TiffError;
+
+// Fetches byte ranges from remote or otherwise expensive storage.
+pub trait RangeFetcher: Send {
+    fn size(&mut self) -> Result<u64, TiffError>;
+    fn fetch(&mut self, offset: u64, len: u64) -> Result<Vec<u8>, TiffError>;
+}
+
+// Read + Seek adapter over a RangeFetcher. Data is fetched in aligned blocks kept in
+// an LRU cache, and runs of missing blocks are fetched with a single request.
+pub struct RangeReader<F: RangeFetcher> {
+    fetcher: F,
+    size: Option<u64>,
+    pos: u64,
+    block_size: u64,
+    max_blocks: usize,
+    blocks: HashMap<u64, Vec<u8>>,
+    order: VecDeque<u64>,
+}
+impl<F: RangeFetcher> RangeReader<F> {
+    pub fn new(fetcher: F) -> Self {
+        // default: 64 KiB blocks, 16 MiB cache
+        Self::with_cache(fetcher, 65536, 256)
+    }
+    pub fn with_cache(fetcher: F, block_size: u64, max_blocks: usize) -> Self {
+        Self{
+            fetcher,
+            size: None,
+            pos: 0,
+            block_size: block_size.max(1),
+            max_blocks: max_blocks.max(1),
+            blocks: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+    pub fn size(&mut self) -> Result<u64, TiffError> {
+        match self.size {
+            Some(size) => Ok(size),
+            None => {
+                let size = self.fetcher.size()?;
+                self.size = Some(size);
+                Ok(size)
+            },
+        }
+    }
+    fn touch(&mut self, block: u64) {
+        if let Some(i) = self.order.iter().position(|b| *b == block) {
+            self.order.remove(i);
+        }
+        self.order.push_back(block);
+    }
+    fn insert(&mut self, block: u64, data: Vec<u8>) {
+        self.blocks.insert(block, data);
+        self.touch(block);
+        while self.order.len() > self.max_blocks {
+            if let Some(old) = self.order.pop_front() {
+                self.blocks.remove(&old);
+            }
+        }
+    }
+    fn load(&mut self, start: u64, end: u64) -> Result<(), TiffError> {
+        let size = self.size()?;
+        let end = end.min(size);
+        if start >= end {
+            return Ok(());
+        }
+        let last = (end - 1) / self.block_size;
+        let mut block = start / self.block_size;
+        while block <= last {
+            if self.blocks.contains_key(&block) {
+                self.touch(block);
+                block += 1;
+                continue;
+            }
+            let first = block;
+            while block <= last && !self.blocks.contains_key(&block) {
+                block += 1;
+            }
+            let offset = first * self.block_size;
+            let len = (block * self.block_size).min(size) - offset;
+            let data = self.fetcher.fetch(offset, len)?;
+            if data.len() as u64 != len {
+                return Err(TiffError::UnexpectedEndOfBuffer);
+            }
+            for (i, chunk) in data.chunks(self.block_size as usize).enumerate() {
+                self.insert(first + i as u64, chunk.to_vec());
+            }
+        }
+        Ok(())
+    }
+    // Loads several (offset, length) ranges, e.g. the tiles of a region, merging
+    // ranges that are at most one block apart into a single request.
+    pub fn prefetch(&mut self, ranges: &[(u64, u64)]) -> Result<(), TiffError> {
+        let mut ranges: Vec<(u64, u64)> = ranges.iter()
+            .filter(|(_, len)| *len > 0)
+            .map(|(offset, len)| (*offset, offset + len))
+            .collect();
+        ranges.sort();
+        let mut merged: Vec<(u64, u64)> = vec![];
+        for (start, end) in ranges {
+            match merged.last_mut() {
+                Some(last) if start <= last.1 + self.block_size => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+        for (start, end) in merged {
+            self.load(start, end)?;
+        }
+        Ok(())
+    }
+}
+impl<F: RangeFetcher> Read for RangeReader<F> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let to_io = |e: TiffError| std::io::Error::other(format!("{:?}", e));
+        let size = self.size().map_err(to_io)?;
+        if buf.is_empty() || self.pos >= size {
+            return Ok(0);
+        }
+        // never ask for more than the cache holds, so the first block survives eviction
+        let limit = self.max_blocks as u64 * self.block_size - self.pos % self.block_size;
+        let end = (self.pos + buf.len() as u64).min(self.pos + limit).min(size);
+        self.load(self.pos, end).map_err(to_io)?;
+        let mut n = 0;
+        while self.pos < end {
+            let block = self.pos / self.block_size;
+            let data = match self.blocks.get(&block) {
+                Some(data) => data,
+                None => break,
+            };
+            let start = (self.pos % self.block_size) as usize;
+            let count = (data.len() - start).min((end - self.pos) as usize);
+            buf[n..n + count].copy_from_slice(&data[start..start + count]);
+            n += count;
+            self.pos += count as u64;
+        }
+        Ok(n)
+    }
+}
+impl<F: RangeFetcher> Seek for RangeReader<F> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let invalid = || std::io::Error::from(std::io::ErrorKind::InvalidInput);
+        let pos = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(delta) => self.pos.checked_add_signed(delta),
+            SeekFrom::End(delta) => {
+                let size = self.size().map_err(|e| std::io::Error::other(format!("{:?}", e)))?;
+                size.checked_add_signed(delta)
+            },
+        };
+        self.pos = pos.ok_or_else(invalid)?;
+        Ok(self.pos)
+    }
+}
+
+#[cfg(feature = "http")]
+pub struct HttpFetcher {
+    url: String,
+    agent: ureq::Agent,
+}
+#[cfg(feature = "http")]
+impl HttpFetcher {
+    pub fn new(url: &str) -> Self {
+        Self{url: String::from(url), agent: ureq::Agent::new_with_defaults()}
+    }
+}
+#[cfg(feature = "http")]
+impl RangeFetcher for HttpFetcher {
+    fn size(&mut self) -> Result<u64, TiffError> {
+        let response = self.agent.head(&self.url).call().or(Err(TiffError::CannotFetchRange))?;
+        let length = response.headers().get("Content-Length").ok_or(TiffError::CannotFetchRange)?;
+        length.to_str().ok().and_then(|v| v.parse().ok()).ok_or(TiffError::CannotFetchRange)
+    }
+    fn fetch(&mut self, offset: u64, len: u64) -> Result<Vec<u8>, TiffError> {
+        if len == 0 {
+            return Ok(vec![]);
+        }
+        let range = format!("bytes={}-{}", offset, offset + len - 1);
+        let mut response = self.agent.get(&self.url).header("Range", &range).call().or(Err(TiffError::CannotFetchRange))?;
+        // a 200 means the server ignored the Range header and would send the whole object
+        if response.status().as_u16() != 206 {
+            return Err(TiffError::CannotFetchRange);
+        }
+        response.body_mut().with_config().limit(u64::MAX).read_to_vec().or(Err(TiffError::CannotFetchRange))
+    }
+}
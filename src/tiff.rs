@@ -1,47 +1,467 @@
 #![allow(dead_code)]
 
+use std::collections::HashSet;
 use std::path::Path;
 use std::fs::File;
-use std::io::{BufReader, Seek, SeekFrom};
-use crate::{TiffError, Header, IFD};
+use std::io::{BufReader, Cursor, Read, Seek, SeekFrom};
+use std::sync::{Arc, Mutex};
+use crate::{TiffError, RangeFetcher, RangeReader, Header, IFD, TiffBytes, ChunkCache, CompressionScheme, MakerNote, CogReport, validate_cog, Pyramid, associate_masks, apply_orientation, unpack_samples};
+use crate::decode::{ChunkDecoder, decode_chunks};
+use crate::ifd::{Baseline, Extension, Tag, TagData, TagID};
+
+pub trait Source: Read + Seek + Send {}
+impl<T: Read + Seek + Send> Source for T {}
+
+// Range sources also take the byte ranges a read is about to touch, to fetch them in few requests.
+trait RangeSource: Source {
+    fn prefetch(&mut self, ranges: &[(u64, u64)]) -> Result<(), TiffError>;
+}
+impl<F: RangeFetcher> RangeSource for RangeReader<F> {
+    fn prefetch(&mut self, ranges: &[(u64, u64)]) -> Result<(), TiffError> {
+        RangeReader::prefetch(self, ranges)
+    }
+}
+
+enum Reader<'a> {
+    Plain(Mutex<Box<dyn Source + 'a>>),
+    Range(Mutex<Box<dyn RangeSource + 'a>>),
+}
+
+#[derive(Debug)]
+pub struct RawChunk {
+    pub data: Vec<u8>,
+    // Compression tag value, kept as a number so unknown schemes pass through
+    pub compression: u64,
+    pub jpeg_tables: Option<Vec<u8>>,
+}
+impl RawChunk {
+    pub fn scheme(&self) -> Option<CompressionScheme> {
+        CompressionScheme::from_number(self.compression)
+    }
+}
+impl std::fmt::Debug for Reader<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        f.write_str("Reader")
+    }
+}
 
 #[derive(Debug, Default)]
-pub struct Tiff {
+pub struct Tiff<'a> {
     pub path: String,
     pub header: Header,
     pub ifd: Vec<IFD>,
+    // bulky out-of-line tag values above this many bytes are left unread by read_frame and iter
+    pub defer_threshold: Option<usize>,
+    reader: Option<Reader<'a>>,
+    cache: Option<Mutex<ChunkCache>>,
+    #[cfg(feature = "mmap")]
+    mmap: Option<memmap2::Mmap>,
 }
-impl Tiff {
+impl<'a> Tiff<'a> {
     pub fn from_path(path: &Path, skip: bool) -> Result<Self, TiffError> {
         let file = File::open(path).or(Err(TiffError::CannotOpenFile))?;
-        let mut buffer = BufReader::new(file);
-        let header = Header::from_buffer(&mut buffer)?;
+        let mut tiff = Self::from_reader(BufReader::new(file), skip)?;
+        tiff.path = String::from(path.to_str().unwrap_or(""));
+        Ok(tiff)
+    }
+    #[cfg(feature = "mmap")]
+    pub fn from_mmap(path: &Path, skip: bool) -> Result<Self, TiffError> {
+        let file = File::open(path).or(Err(TiffError::CannotOpenFile))?;
+        // SAFETY: the file must not be truncated or modified while it is mapped.
+        let mmap = unsafe { memmap2::Mmap::map(&file) }.or(Err(TiffError::CannotOpenFile))?;
+        let mut tiff = Self::from_buffer(&mut Cursor::new(&mmap[..]), skip)?;
+        tiff.path = String::from(path.to_str().unwrap_or(""));
+        tiff.mmap = Some(mmap);
+        Ok(tiff)
+    }
+    #[cfg(feature = "http")]
+    pub fn from_url(url: &str, skip: bool) -> Result<Self, TiffError> {
+        let reader = RangeReader::new(crate::range::HttpFetcher::new(url));
+        let mut tiff = Self::from_range_reader(reader, skip)?;
+        tiff.path = String::from(url);
+        Ok(tiff)
+    }
+    // Reads the header and the first IFD only; the rest can be walked with `iter`.
+    pub fn from_path_first(path: &Path, skip: bool) -> Result<Self, TiffError> {
+        let file = File::open(path).or(Err(TiffError::CannotOpenFile))?;
+        let mut tiff = Self::from_reader_first(BufReader::new(file), skip)?;
+        tiff.path = String::from(path.to_str().unwrap_or(""));
+        Ok(tiff)
+    }
+    // Parses from `buffer` and keeps it for later frame reads. The reader may borrow,
+    // e.g. `&mut File` or `Cursor<&[u8]>`, in which case the Tiff borrows it too.
+    pub fn from_reader<R: Read + Seek + Send + 'a>(mut buffer: R, skip: bool) -> Result<Self, TiffError> {
+        let mut tiff = Self::from_buffer(&mut buffer, skip)?;
+        tiff.reader = Some(Reader::Plain(Mutex::new(Box::new(buffer))));
+        Ok(tiff)
+    }
+    // Like `from_reader`, leaving bulky out-of-line values above `threshold` bytes unread
+    // (see `defer_threshold`) in the IFDs parsed at open as well.
+    pub fn from_reader_with_threshold<R: Read + Seek + Send + 'a>(mut buffer: R, skip: bool, threshold: usize) -> Result<Self, TiffError> {
+        let mut tiff = Self::from_buffer_limit(&mut buffer, skip, usize::MAX, Some(threshold))?;
+        tiff.reader = Some(Reader::Plain(Mutex::new(Box::new(buffer))));
+        Ok(tiff)
+    }
+    // Like `from_reader`; `read_chunks` also asks the reader to fetch all the chunks it needs
+    // up front, in as few requests as the block cache allows.
+    pub fn from_range_reader<F: RangeFetcher + 'a>(mut buffer: RangeReader<F>, skip: bool) -> Result<Self, TiffError> {
+        let mut tiff = Self::from_buffer(&mut buffer, skip)?;
+        tiff.reader = Some(Reader::Range(Mutex::new(Box::new(buffer))));
+        Ok(tiff)
+    }
+    pub fn from_reader_first<R: Read + Seek + Send + 'a>(mut buffer: R, skip: bool) -> Result<Self, TiffError> {
+        let mut tiff = Self::from_buffer_limit(&mut buffer, skip, 1, None)?;
+        tiff.reader = Some(Reader::Plain(Mutex::new(Box::new(buffer))));
+        Ok(tiff)
+    }
+    // Parses from a borrowed `buffer` without keeping it: frames must then be read with
+    // `read_frame_from`, and the other reads fail with NoReader. Use `from_reader(&mut buffer)`
+    // to keep reading from a borrowed buffer.
+    pub fn from_buffer<R: Read + Seek>(buffer: &mut R, skip: bool) -> Result<Self, TiffError> {
+        Self::from_buffer_limit(buffer, skip, usize::MAX, None)
+    }
+    fn from_buffer_limit<R: Read + Seek>(buffer: &mut R, skip: bool, limit: usize, defer_threshold: Option<usize>) -> Result<Self, TiffError> {
+        buffer.seek(SeekFrom::Start(0)).or(Err(TiffError::UnexpectedEndOfBuffer))?;
+        let header = Header::from_buffer(buffer)?;
         let btf = header.is_btf();
         let le = header.is_le();
         let mut next_ifd = header.first_ifd;
         let mut ifd: Vec<IFD> = vec![];
-        while next_ifd != 0 {
+        let mut visited: HashSet<u64> = HashSet::new();
+        while next_ifd != 0 && ifd.len() < limit {
+            if !visited.insert(next_ifd) {
+                return Err(TiffError::CircularIFDChain);
+            }
             buffer.seek(SeekFrom::Start(next_ifd)).or(Err(TiffError::UnexpectedEndOfBuffer))?;
-            let directory = IFD::from_buffer(&mut buffer, btf, le, skip)?;
+            let directory = IFD::from_buffer_deferred(buffer, btf, le, skip, defer_threshold.unwrap_or(usize::MAX))?;
             next_ifd = directory.next_ifd;
             ifd.push(directory);
         }
-        Ok(Self{path: String::from(path.to_str().unwrap_or("")), header, ifd})
+        Ok(Self{
+            path: String::new(),
+            header,
+            ifd,
+            defer_threshold,
+            reader: None,
+            cache: None,
+            #[cfg(feature = "mmap")]
+            mmap: None,
+        })
+    }
+    pub fn from_bytes(data: &[u8]) -> Result<TiffBytes<'_>, TiffError> {
+        TiffBytes::from_bytes(data)
     }
     pub fn len(&self) -> usize {
         self.ifd.len()
     }
+    // Walks the whole IFD chain from the header, parsing each IFD only when reached.
+    pub fn iter(&self, skip: bool) -> IFDIter<'_, 'a> {
+        IFDIter{tiff: self, next_ifd: self.header.first_ifd, skip, visited: HashSet::new()}
+    }
+    // Mapped file contents, when opened with `from_mmap`.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        #[cfg(feature = "mmap")]
+        if let Some(mmap) = &self.mmap {
+            return Some(&mmap[..]);
+        }
+        None
+    }
+    // Zero-copy view of a mapped file; strip and tile fetches are slice accesses
+    // and the view can be shared between threads.
+    pub fn mapped(&self) -> Result<TiffBytes<'_>, TiffError> {
+        match self.as_bytes() {
+            Some(data) => TiffBytes::from_bytes(data),
+            None => Err(TiffError::CannotOpenFile),
+        }
+    }
+    // Runs `f` on the reader kept by this Tiff. Access is serialized, so a Tiff can be
+    // shared between threads; mapped files are read without locking.
+    pub fn with_reader<T, F>(&self, f: F) -> Result<T, TiffError>
+    where F: FnOnce(&mut dyn Source) -> Result<T, TiffError> {
+        if let Some(data) = self.as_bytes() {
+            return f(&mut Cursor::new(data));
+        }
+        match &self.reader {
+            Some(Reader::Plain(reader)) => {
+                let mut reader = reader.lock().or(Err(TiffError::UnknownBufferError))?;
+                f(reader.as_mut())
+            },
+            Some(Reader::Range(reader)) => {
+                let mut reader = reader.lock().or(Err(TiffError::UnknownBufferError))?;
+                f(reader.as_mut())
+            },
+            None => Err(TiffError::NoReader),
+        }
+    }
     pub fn read_frame(&self, index: usize) -> Result<IFD, TiffError> {
+        self.with_reader(|mut buffer| self.read_frame_from(&mut buffer, index))
+    }
+    pub fn read_frame_from<R: Read + Seek>(&self, buffer: &mut R, index: usize) -> Result<IFD, TiffError> {
         if index >= self.len() {
             return Err(TiffError::InvalidIndex);
         }
-        let file = File::open(&self.path).or(Err(TiffError::CannotOpenFile))?;
-        let mut buffer = BufReader::new(file);
         let btf = self.header.is_btf();
         let le = self.header.is_le();
         let pos = self.ifd[index].pos;
+        let threshold = self.defer_threshold.unwrap_or(usize::MAX);
         buffer.seek(SeekFrom::Start(pos)).or(Err(TiffError::UnexpectedEndOfBuffer))?;
-        let directory = IFD::from_buffer(&mut buffer, btf, le, false)?;
+        let directory = IFD::from_buffer_deferred(buffer, btf, le, false, threshold)?;
         Ok(directory)
     }
+    // Materializes a tag value left unread because of `defer_threshold`.
+    pub fn load_tag<'t>(&self, tag: &'t Tag) -> Result<&'t TagData, TiffError> {
+        let le = self.header.is_le();
+        self.with_reader(|mut buffer| tag.load(&mut buffer, le))
+    }
+    pub fn pyramid(&self) -> Result<Pyramid, TiffError> {
+        Pyramid::from_tiff(self)
+    }
+    // Index of the transparency mask IFD of each top level IFD, if it has one.
+    pub fn masks(&self) -> Result<Vec<Option<usize>>, TiffError> {
+        let mut frames: Vec<IFD> = vec![];
+        for index in 0..self.len() {
+            frames.push(self.read_frame(index)?);
+        }
+        Ok(associate_masks(&frames))
+    }
+    pub fn validate_cog(&self) -> Result<CogReport, TiffError> {
+        validate_cog(self)
+    }
+    pub fn maker_note(&self, index: usize) -> Result<MakerNote, TiffError> {
+        let ifd = self.read_frame(index)?;
+        let le = self.header.is_le();
+        self.with_reader(|mut buffer| MakerNote::from_ifd(&ifd, &mut buffer, le))
+    }
+    // Keeps up to `budget` bytes of decoded strips/tiles; 0 disables the cache.
+    pub fn set_cache_budget(&mut self, budget: usize) {
+        self.cache = match budget {
+            0 => None,
+            _ => Some(Mutex::new(ChunkCache::new(budget))),
+        };
+    }
+    // Offsets and byte counts of all the strips or tiles, given their tags.
+    fn chunk_locations(&self, ifd: &IFD, offsets: TagID, byte_counts: TagID) -> Result<(Vec<u64>, Vec<u64>), TiffError> {
+        let offsets = self.load_tag(ifd.get_tag(offsets)?)?.as_unsigned_integers()?;
+        let byte_counts = self.load_tag(ifd.get_tag(byte_counts)?)?.as_unsigned_integers()?;
+        Ok((offsets, byte_counts))
+    }
+    fn read_raw(&self, offsets: &[u64], byte_counts: &[u64], index: usize) -> Result<Vec<u8>, TiffError> {
+        let (offset, byte_count) = match (offsets.get(index), byte_counts.get(index)) {
+            (Some(offset), Some(byte_count)) => (*offset, *byte_count as usize),
+            _ => return Err(TiffError::InvalidIndex),
+        };
+        // sparse chunk, decoded as DefaultImageColor
+        if offset == 0 || byte_count == 0 {
+            return Ok(vec![]);
+        }
+        self.with_reader(|buffer| {
+            let mut data = vec![0u8; byte_count];
+            buffer.seek(SeekFrom::Start(offset)).or(Err(TiffError::UnexpectedEndOfBuffer))?;
+            buffer.read_exact(&mut data).or(Err(TiffError::UnexpectedEndOfBuffer))?;
+            Ok(data)
+        })
+    }
+    fn raw_chunk(&self, ifd: &IFD, data: Vec<u8>) -> Result<RawChunk, TiffError> {
+        let jpeg_tables = match ifd.get_tag(TagID::ExtensionTag(Extension::JPEGTables)) {
+            Ok(tag) => Some(self.load_tag(tag)?.as_unsigned_integers()?.iter().map(|v| *v as u8).collect()),
+            Err(_) => None,
+        };
+        Ok(RawChunk{data, compression: ifd.compression_number()?, jpeg_tables})
+    }
+    // Stored (still compressed) bytes of one strip, for pass-through or external codecs.
+    pub fn read_raw_strip(&self, ifd: &IFD, index: usize) -> Result<RawChunk, TiffError> {
+        let (offsets, byte_counts) = self.chunk_locations(ifd, TagID::BaselineTag(Baseline::StripOffsets), TagID::BaselineTag(Baseline::StripByteCounts))?;
+        let data = self.read_raw(&offsets, &byte_counts, index)?;
+        self.raw_chunk(ifd, data)
+    }
+    // Stored (still compressed) bytes of one tile, for pass-through or external codecs.
+    pub fn read_raw_tile(&self, ifd: &IFD, index: usize) -> Result<RawChunk, TiffError> {
+        let (offsets, byte_counts) = self.chunk_locations(ifd, TagID::ExtensionTag(Extension::TileOffsets), TagID::ExtensionTag(Extension::TileByteCounts))?;
+        let data = self.read_raw(&offsets, &byte_counts, index)?;
+        self.raw_chunk(ifd, data)
+    }
+    // Reads and decodes one strip (or tile, for tiled images) of `ifd`, going
+    // through the chunk cache when one is set.
+    pub fn read_chunk(&self, ifd: &IFD, index: usize) -> Result<Arc<Vec<u8>>, TiffError> {
+        let mut chunks = self.read_chunks(ifd, &[index])?;
+        chunks.pop().ok_or(TiffError::InvalidIndex)
+    }
+    // Like `read_chunk` for several chunks: stored bytes are fetched one after the other,
+    // then decoded together (in parallel with the "parallel" feature).
+    pub fn read_chunks(&self, ifd: &IFD, indices: &[usize]) -> Result<Vec<Arc<Vec<u8>>>, TiffError> {
+        let mut chunks: Vec<Option<Arc<Vec<u8>>>> = vec![None; indices.len()];
+        if let Some(cache) = &self.cache {
+            let mut cache = cache.lock().or(Err(TiffError::UnknownBufferError))?;
+            for (chunk, index) in chunks.iter_mut().zip(indices) {
+                *chunk = cache.get((ifd.pos, *index));
+            }
+        }
+        let missing: Vec<usize> = (0..indices.len()).filter(|i| chunks[*i].is_none()).collect();
+        let (offsets, byte_counts) = chunk_tags(ifd);
+        let (offsets, byte_counts) = self.chunk_locations(ifd, offsets, byte_counts)?;
+        if let Some(Reader::Range(reader)) = &self.reader {
+            let ranges: Vec<(u64, u64)> = missing.iter()
+                .filter_map(|i| Some((*offsets.get(indices[*i])?, *byte_counts.get(indices[*i])?)))
+                .collect();
+            reader.lock().or(Err(TiffError::UnknownBufferError))?.prefetch(&ranges)?;
+        }
+        let mut raw: Vec<Vec<u8>> = vec![];
+        for i in &missing {
+            raw.push(self.read_raw(&offsets, &byte_counts, indices[*i])?);
+        }
+        let decoder = ChunkDecoder::from_ifd(ifd, self.header.is_le())?;
+        let decoded = decode_chunks(&decoder, raw)?;
+        for (i, data) in missing.into_iter().zip(decoded) {
+            let data = Arc::new(data);
+            if let Some(cache) = &self.cache {
+                cache.lock().or(Err(TiffError::UnknownBufferError))?.insert((ifd.pos, indices[i]), data.clone());
+            }
+            chunks[i] = Some(data);
+        }
+        Ok(chunks.into_iter().flatten().collect())
+    }
+    // Reads a rectangle of chunky, byte-aligned pixels from the strips or tiles it covers.
+    pub fn read_region(&self, ifd: &IFD, x: u64, y: u64, width: u64, height: u64) -> Result<Vec<u8>, TiffError> {
+        let cells = region_chunks(ifd, x, y, width, height)?;
+        let indices: Vec<usize> = cells.iter().map(|(_, _, index)| *index).collect();
+        let chunks = self.read_chunks(ifd, &indices)?;
+        assemble_region(ifd, x, y, width, height, &cells, &chunks)
+    }
+    pub fn read_image(&self, ifd: &IFD) -> Result<Vec<u8>, TiffError> {
+        self.read_region(ifd, 0, 0, ifd.width()?, ifd.height()?)
+    }
+    // The whole image in display order per its Orientation tag, with its (width, height)
+    // after rotation. `read_image` is the raw read in storage order.
+    pub fn read_image_oriented(&self, ifd: &IFD) -> Result<(Vec<u8>, usize, usize), TiffError> {
+        let data = self.read_image(ifd)?;
+        apply_orientation(&data, ifd.width()? as usize, ifd.height()? as usize, ifd.pixel_size()?, &ifd.orientation()?)
+    }
+    // Reads a rectangle of a transparency mask; the mask has the size of its image, so
+    // the same (x, y, width, height) as a `read_region` on the image lines up pixel for
+    // pixel. 1 bit masks are unpacked, and for 8 bit masks any non-zero value is opaque.
+    pub fn read_mask_region(&self, mask: &IFD, x: u64, y: u64, width: u64, height: u64) -> Result<Vec<bool>, TiffError> {
+        match mask.bpp()?.as_slice() {
+            [8] => return Ok(self.read_region(mask, x, y, width, height)?.into_iter().map(|v| v != 0).collect()),
+            [1] => (),
+            _ => return Err(TiffError::NotSupportedSampleLayout),
+        }
+        let cells = region_chunks(mask, x, y, width, height)?;
+        let indices: Vec<usize> = cells.iter().map(|(_, _, index)| *index).collect();
+        let chunks = self.read_chunks(mask, &indices)?;
+        let (chunk_width, chunk_height, _) = mask.chunk_layout()?;
+        let mut out = vec![false; (width * height) as usize];
+        // every row of a chunk starts on a byte boundary
+        let stride = chunk_width.div_ceil(8) as usize;
+        for ((cx, cy, _), chunk) in cells.into_iter().zip(chunks) {
+            let x0 = x.max(cx * chunk_width);
+            let x1 = (x + width).min((cx + 1) * chunk_width);
+            let y0 = y.max(cy * chunk_height);
+            let y1 = (y + height).min((cy + 1) * chunk_height);
+            for row in y0..y1 {
+                let line = (row - cy * chunk_height) as usize * stride;
+                for col in x0..x1 {
+                    let bit = (col - cx * chunk_width) as usize;
+                    let byte = chunk.get(line + bit / 8).ok_or(TiffError::UnexpectedBufferSize)?;
+                    out[((row - y) * width + (col - x)) as usize] = byte & (0x80 >> (bit % 8)) != 0;
+                }
+            }
+        }
+        Ok(out)
+    }
+    // Region samples along with a per-pixel validity mask from GDAL_NODATA.
+    pub fn read_region_masked(&self, ifd: &IFD, x: u64, y: u64, width: u64, height: u64) -> Result<(Vec<u8>, Vec<bool>), TiffError> {
+        let nodata = ifd.gdal_nodata()?;
+        let data = self.read_region(ifd, x, y, width, height)?;
+        let bits = ifd.bpp()?.first().copied().unwrap_or(1);
+        let mask = nodata.mask(&data, bits, ifd.samples()? as usize, self.header.is_le())?;
+        Ok((data, mask))
+    }
+    // Region samples as unsigned values, one per sample, for `apply_decode`,
+    // `apply_transfer_function` and `apply_gray_response`.
+    pub fn read_region_samples(&self, ifd: &IFD, x: u64, y: u64, width: u64, height: u64) -> Result<Vec<u64>, TiffError> {
+        let data = self.read_region(ifd, x, y, width, height)?;
+        unpack_samples(&data, &ifd.bpp()?, self.header.is_le())
+    }
+}
+
+// Offsets and byte counts tags of the tiles, or of the strips of an untiled image.
+pub(crate) fn chunk_tags(ifd: &IFD) -> (TagID, TagID) {
+    if ifd.is_tiled() {
+        (TagID::ExtensionTag(Extension::TileOffsets), TagID::ExtensionTag(Extension::TileByteCounts))
+    } else {
+        (TagID::BaselineTag(Baseline::StripOffsets), TagID::BaselineTag(Baseline::StripByteCounts))
+    }
+}
+
+// (column, row, index) of the strips or tiles covering a region of `ifd`.
+pub(crate) fn region_chunks(ifd: &IFD, x: u64, y: u64, width: u64, height: u64) -> Result<Vec<(u64, u64, usize)>, TiffError> {
+    if x + width > ifd.width()? || y + height > ifd.height()? {
+        return Err(TiffError::InvalidIndex);
+    }
+    let (chunk_width, chunk_height, across) = ifd.chunk_layout()?;
+    let mut cells: Vec<(u64, u64, usize)> = vec![];
+    if width == 0 || height == 0 {
+        return Ok(cells);
+    }
+    for cy in y / chunk_height..=(y + height - 1) / chunk_height {
+        for cx in x / chunk_width..=(x + width - 1) / chunk_width {
+            cells.push((cx, cy, (cy * across + cx) as usize));
+        }
+    }
+    Ok(cells)
+}
+
+// Copies the parts of the decoded `chunks` of `cells` that fall inside a region.
+pub(crate) fn assemble_region(ifd: &IFD, x: u64, y: u64, width: u64, height: u64, cells: &[(u64, u64, usize)], chunks: &[Arc<Vec<u8>>]) -> Result<Vec<u8>, TiffError> {
+    let pixel_size = ifd.pixel_size()?;
+    let (chunk_width, chunk_height, _) = ifd.chunk_layout()?;
+    let mut out = vec![0u8; (width * height) as usize * pixel_size];
+    let stride = chunk_width as usize * pixel_size;
+    for ((cx, cy, _), chunk) in cells.iter().zip(chunks) {
+        let x0 = x.max(cx * chunk_width);
+        let x1 = (x + width).min((cx + 1) * chunk_width);
+        let y0 = y.max(cy * chunk_height);
+        let y1 = (y + height).min((cy + 1) * chunk_height);
+        let len = (x1 - x0) as usize * pixel_size;
+        for row in y0..y1 {
+            let src = (row - cy * chunk_height) as usize * stride + (x0 - cx * chunk_width) as usize * pixel_size;
+            let dst = ((row - y) * width + (x0 - x)) as usize * pixel_size;
+            let line = chunk.get(src..src + len).ok_or(TiffError::UnexpectedBufferSize)?;
+            out[dst..dst + len].copy_from_slice(line);
+        }
+    }
+    Ok(out)
+}
+
+pub struct IFDIter<'a, 'r> {
+    tiff: &'a Tiff<'r>,
+    next_ifd: u64,
+    skip: bool,
+    // offsets already walked, to stop on a next IFD pointer that loops back
+    visited: HashSet<u64>,
+}
+impl Iterator for IFDIter<'_, '_> {
+    type Item = Result<IFD, TiffError>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next_ifd == 0 {
+            return None;
+        }
+        if !self.visited.insert(self.next_ifd) {
+            self.next_ifd = 0;
+            return Some(Err(TiffError::CircularIFDChain));
+        }
+        let btf = self.tiff.header.is_btf();
+        let le = self.tiff.header.is_le();
+        let pos = self.next_ifd;
+        let threshold = self.tiff.defer_threshold.unwrap_or(usize::MAX);
+        let directory = self.tiff.with_reader(|mut buffer| {
+            buffer.seek(SeekFrom::Start(pos)).or(Err(TiffError::UnexpectedEndOfBuffer))?;
+            IFD::from_buffer_deferred(&mut buffer, btf, le, self.skip, threshold)
+        });
+        self.next_ifd = match &directory {
+            Ok(directory) => directory.next_ifd,
+            Err(_) => 0,
+        };
+        Some(directory)
+    }
 }
@@ -0,0 +1,138 @@
+#![allow(dead_code)]
+
+use crate::{TiffError, Orientation, FillOrder};
+
+// Re-orders a decoded, byte-aligned pixel buffer from storage order into display order.
+// The input buffer is left untouched, so it doubles as the raw (unrotated) image.
+// Returns the oriented buffer together with its (width, height), which are swapped
+// for the four transposing orientations.
+pub fn apply_orientation(data: &[u8], width: usize, height: usize, pixel_size: usize, orientation: &Orientation) -> Result<(Vec<u8>, usize, usize), TiffError> {
+    if data.len() != width * height * pixel_size {
+        return Err(TiffError::UnexpectedBufferSize);
+    }
+    let transposed = matches!(orientation,
+        Orientation::LeftTop | Orientation::RightTop | Orientation::RightBottom | Orientation::LeftBottom);
+    let (out_width, out_height) = if transposed { (height, width) } else { (width, height) };
+    let mut out = vec![0u8; data.len()];
+    for y in 0..height {
+        for x in 0..width {
+            let (ox, oy) = match orientation {
+                Orientation::TopLeft     => (x, y),
+                Orientation::TopRight    => (width - 1 - x, y),
+                Orientation::BottomRight => (width - 1 - x, height - 1 - y),
+                Orientation::BottomLeft  => (x, height - 1 - y),
+                Orientation::LeftTop     => (y, x),
+                Orientation::RightTop    => (height - 1 - y, x),
+                Orientation::RightBottom => (height - 1 - y, width - 1 - x),
+                Orientation::LeftBottom  => (y, width - 1 - x),
+            };
+            let src = (y * width + x) * pixel_size;
+            let dst = (oy * out_width + ox) * pixel_size;
+            out[dst..dst + pixel_size].copy_from_slice(&data[src..src + pixel_size]);
+        }
+    }
+    Ok((out, out_width, out_height))
+}
+
+// Brings strip or tile bytes into most-significant-bit-first order in place.
+// Must run on the bytes as stored, before any decompression.
+pub fn apply_fill_order(data: &mut [u8], fill_order: &FillOrder) {
+    if *fill_order == FillOrder::LsbToMsb {
+        for byte in data.iter_mut() {
+            *byte = byte.reverse_bits();
+        }
+    }
+}
+
+// Turns WhiteIsZero unsigned integer samples into BlackIsZero in place.
+// Flipping every bit maps v to (2^n - 1) - v for any bits-per-sample, packed or not.
+pub fn invert_samples(data: &mut [u8]) {
+    for byte in data.iter_mut() {
+        *byte = !*byte;
+    }
+}
+
+// Maps interleaved unsigned sample values onto the [min, max] ranges of the Decode tag,
+// one pair per sample. A pair with min > max reverses the ramp.
+pub fn apply_decode(values: &[u64], bits_per_sample: u64, decode: &[f64]) -> Result<Vec<f64>, TiffError> {
+    if decode.is_empty() || !decode.len().is_multiple_of(2) || !values.len().is_multiple_of(decode.len() / 2) {
+        return Err(TiffError::UnexpectedBufferSize);
+    }
+    let samples = decode.len() / 2;
+    let max = ((1u128 << bits_per_sample) - 1) as f64;
+    let mut results = vec![0.0f64; values.len()];
+    for (i, value) in values.iter().enumerate() {
+        let (min_out, max_out) = (decode[2 * (i % samples)], decode[2 * (i % samples) + 1]);
+        results[i] = min_out + (*value as f64) * (max_out - min_out) / max;
+    }
+    Ok(results)
+}
+
+// Paints every pixel of a byte-aligned buffer with DefaultImageColor, e.g. for strips
+// that are absent from the file.
+pub fn fill_default_color(data: &mut [u8], color: &[u8]) -> Result<(), TiffError> {
+    if color.is_empty() || !data.len().is_multiple_of(color.len()) {
+        return Err(TiffError::UnexpectedBufferSize);
+    }
+    for pixel in data.chunks_exact_mut(color.len()) {
+        pixel.copy_from_slice(color);
+    }
+    Ok(())
+}
+
+// Splits decoded, interleaved samples in file byte order (as from `Tiff::read_region`)
+// into unsigned values for the Decode, TransferFunction and GrayResponseCurve helpers.
+pub fn unpack_samples(data: &[u8], bits_per_sample: &[u64], le: bool) -> Result<Vec<u64>, TiffError> {
+    if bits_per_sample.is_empty() || bits_per_sample.iter().any(|b| !(8..=64).contains(b) || !b.is_multiple_of(8)) {
+        return Err(TiffError::NotSupportedSampleLayout);
+    }
+    let pixel_size: usize = bits_per_sample.iter().map(|b| (*b / 8) as usize).sum();
+    if !data.len().is_multiple_of(pixel_size) {
+        return Err(TiffError::UnexpectedBufferSize);
+    }
+    let mut values = Vec::with_capacity(data.len() / pixel_size * bits_per_sample.len());
+    for pixel in data.chunks_exact(pixel_size) {
+        let mut pos = 0;
+        for bits in bits_per_sample {
+            let sample = &pixel[pos..pos + (*bits / 8) as usize];
+            pos += sample.len();
+            let value = if le {
+                sample.iter().rev().fold(0u64, |value, byte| value << 8 | *byte as u64)
+            } else {
+                sample.iter().fold(0u64, |value, byte| value << 8 | *byte as u64)
+            };
+            values.push(value);
+        }
+    }
+    Ok(values)
+}
+
+fn apply_lookup<T: Copy>(values: &[u64], tables: &[&[T]]) -> Result<Vec<T>, TiffError> {
+    if tables.is_empty() || !values.len().is_multiple_of(tables.len()) {
+        return Err(TiffError::UnexpectedBufferSize);
+    }
+    let mut results = Vec::with_capacity(values.len());
+    for (i, value) in values.iter().enumerate() {
+        let table = tables[i % tables.len()];
+        match table.get(*value as usize) {
+            Some(v) => results.push(*v),
+            None => return Err(TiffError::InvalidIndex),
+        }
+    }
+    Ok(results)
+}
+
+// Linearizes interleaved sample values through TransferFunction tables into [0, 1].
+// A single table applies to every sample; otherwise there is one table per sample.
+pub fn apply_transfer_function(values: &[u64], tables: &[Vec<u64>]) -> Result<Vec<f64>, TiffError> {
+    let tables: Vec<Vec<f64>> = tables.iter()
+        .map(|table| table.iter().map(|v| *v as f64 / 65535.0).collect())
+        .collect();
+    let tables: Vec<&[f64]> = tables.iter().map(|table| table.as_slice()).collect();
+    apply_lookup(values, &tables)
+}
+
+// Converts grayscale sample values into optical densities through GrayResponseCurve.
+pub fn apply_gray_response(values: &[u64], curve: &[f64]) -> Result<Vec<f64>, TiffError> {
+    apply_lookup(values, &[curve])
+}
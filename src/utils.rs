@@ -3,14 +3,10 @@ macro_rules! buffer_as {
     ($buffer:expr, $typ:ty, $le:expr) => {
         {
             let mut data = [0u8; std::mem::size_of::<$typ>()];
-            if let Ok(n) = $buffer.read(&mut data) {
-                if n != std::mem::size_of::<$typ>() {
-                    Err(TiffError::UnexpectedEndOfBuffer)
-                } else {
-                    match $le {
-                        true  => Ok(<$typ>::from_le_bytes(data)),
-                        false => Ok(<$typ>::from_be_bytes(data)),
-                    }
+            if $buffer.read_exact(&mut data).is_ok() {
+                match $le {
+                    true  => Ok(<$typ>::from_le_bytes(data)),
+                    false => Ok(<$typ>::from_be_bytes(data)),
                 }
             } else {
                 Err(TiffError::UnexpectedEndOfBuffer)